@@ -0,0 +1,278 @@
+use crate::config::Config;
+use crate::exit::ExitCode;
+use crate::paths::Paths;
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub struct Args {
+    pub host: String,
+    pub port: String,
+    pub username: Option<String>,
+    pub overwrite: bool,
+    // Open received files with the default application
+    pub open: bool,
+    pub script: Option<String>,
+    pub keep_going: bool,
+    // Check the script's glide lines without connecting to the server
+    pub dry_run: bool,
+    pub json: bool,
+    // Only errors and results are printed. Always set under --json.
+    pub quiet: bool,
+    pub no_pager: bool,
+    pub reconnect: bool,
+    // 0 by default, 1 for -v and 2 for -vv
+    pub verbosity: u8,
+    pub connect_timeout: Duration,
+    pub io_timeout: Duration,
+    // How often to check the connection while waiting at the prompt
+    pub keepalive: Option<Duration>,
+    // Abandon a download when no data arrives for this long
+    pub transfer_timeout: Duration,
+    // Announce new incoming requests while the prompt is idle
+    pub notify: bool,
+    // None when transfer logging is turned off
+    pub log_file: Option<PathBuf>,
+    // Where received files are saved
+    pub output_dir: PathBuf,
+    // The prompt, with {user}, {host} and {cwd} filled in when it's shown
+    pub prompt: String,
+}
+
+impl Args {
+    pub fn parse(config: Config, paths: Option<&Paths>) -> Result<Args, String> {
+        let mut positional = Vec::new();
+        let mut profile = None;
+        let mut username = None;
+        let mut overwrite = false;
+        let mut open = false;
+        let mut script = None;
+        let mut keep_going = false;
+        let mut dry_run = false;
+        let mut json = false;
+        let mut quiet = false;
+        let mut no_pager = false;
+        let mut reconnect = false;
+        let mut verbosity = 0u8;
+        let mut connect_timeout = None;
+        let mut io_timeout = None;
+        let mut keepalive = None;
+        let mut no_notify = false;
+        let mut transfer_timeout = None;
+        let mut log_file = None;
+        let mut no_log = false;
+        let mut output_dir = None;
+        let mut prompt = None;
+
+        let mut iter = env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--profile" | "-p" => profile = Some(value(&mut iter, &arg)?),
+                "--username" | "-u" => username = Some(value(&mut iter, &arg)?),
+                "--overwrite" => overwrite = true,
+                "--open" => open = true,
+                "--script" => script = Some(value(&mut iter, &arg)?),
+                "--keep-going" => keep_going = true,
+                "--dry-run" => dry_run = true,
+                "--json" => json = true,
+                "--quiet" | "-q" => quiet = true,
+                "--no-pager" => no_pager = true,
+                "--reconnect" => reconnect = true,
+                "--verbose" | "-v" => verbosity = verbosity.saturating_add(1),
+                "-vv" => verbosity = verbosity.saturating_add(2),
+                "--connect-timeout" => {
+                    connect_timeout = Some(seconds(value(&mut iter, &arg)?, &arg)?)
+                }
+                "--io-timeout" => io_timeout = Some(seconds(value(&mut iter, &arg)?, &arg)?),
+                "--keepalive" => keepalive = Some(seconds(value(&mut iter, &arg)?, &arg)?),
+                "--no-notify" => no_notify = true,
+                "--transfer-timeout" => {
+                    transfer_timeout = Some(seconds(value(&mut iter, &arg)?, &arg)?)
+                }
+                "--log-file" => log_file = Some(PathBuf::from(value(&mut iter, &arg)?)),
+                "--no-log" => no_log = true,
+                "--output-dir" => output_dir = Some(PathBuf::from(value(&mut iter, &arg)?)),
+                "--prompt" => prompt = Some(value(&mut iter, &arg)?),
+                // Already picked up by `config_dir` before the config was loaded
+                "--config-dir" => {
+                    value(&mut iter, &arg)?;
+                }
+                flag if flag.starts_with("--") => {
+                    return Err(format!("Unknown option '{}'", flag));
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        // Pick the server profile before anything falls back to the config file
+        let config = match profile {
+            Some(name) => config.select_profile(&name)?,
+            None => config,
+        };
+
+        // Positional arguments take precedence over the config file
+        let mut positional = positional.into_iter();
+        let host = positional.next().or(config.host).ok_or_else(|| {
+            "Expected a HOST, either as an argument or in the config file".to_string()
+        })?;
+        let port = positional
+            .next()
+            .or(config.port.map(|port| port.to_string()))
+            .ok_or_else(|| {
+                "Expected a PORT, either as an argument or in the config file".to_string()
+            })?;
+
+        if let Some(extra) = positional.next() {
+            return Err(format!("Unexpected argument '{}'", extra));
+        }
+
+        if dry_run && script.is_none() {
+            return Err("--dry-run checks a script, so it needs --script".to_string());
+        }
+
+        let connect_timeout = match (connect_timeout, config.connect_timeout) {
+            (Some(timeout), _) => timeout,
+            (None, Some(secs)) => seconds(secs.to_string(), "connect_timeout")?,
+            (None, None) => Duration::from_secs(10),
+        };
+        let io_timeout = match (io_timeout, config.io_timeout) {
+            (Some(timeout), _) => timeout,
+            (None, Some(secs)) => seconds(secs.to_string(), "io_timeout")?,
+            (None, None) => Duration::from_secs(30),
+        };
+        let transfer_timeout = match (transfer_timeout, config.transfer_timeout) {
+            (Some(timeout), _) => timeout,
+            (None, Some(secs)) => seconds(secs.to_string(), "transfer_timeout")?,
+            (None, None) => Duration::from_secs(60),
+        };
+        let keepalive = match (keepalive, config.keepalive) {
+            (Some(interval), _) => Some(interval),
+            (None, Some(secs)) => Some(seconds(secs.to_string(), "keepalive")?),
+            (None, None) => None,
+        };
+
+        Ok(Args {
+            host,
+            port,
+            username: username.or(config.username),
+            overwrite: overwrite || config.overwrite.unwrap_or(false),
+            open,
+            script,
+            keep_going,
+            dry_run,
+            json,
+            quiet: quiet || json,
+            no_pager,
+            reconnect: reconnect || config.reconnect.unwrap_or(false),
+            verbosity: match (verbosity, config.verbose) {
+                (0, Some(true)) => 1,
+                (verbosity, _) => verbosity,
+            },
+            connect_timeout,
+            io_timeout,
+            keepalive,
+            transfer_timeout,
+            notify: !no_notify,
+            log_file: if no_log {
+                None
+            } else {
+                log_file
+                    .or(config.log_file)
+                    .or_else(|| paths.map(|paths| paths.transfer_log.clone()))
+            },
+            output_dir: output_dir
+                .or(config.output_dir)
+                .unwrap_or_else(|| PathBuf::from(".")),
+            prompt: prompt
+                .or(config.prompt)
+                .unwrap_or_else(|| "glide@{user}> ".to_string()),
+        })
+    }
+}
+
+// The config directory has to be known before the config file can be read,
+// so it is looked up ahead of the full parse
+pub fn config_dir() -> Result<Option<PathBuf>, String> {
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--config-dir" {
+            return value(&mut iter, &arg).map(|dir| Some(PathBuf::from(dir)));
+        }
+    }
+
+    Ok(None)
+}
+
+// --version is answered before anything else, so it works without a server
+pub fn version_requested() -> bool {
+    env::args()
+        .skip(1)
+        .any(|arg| arg == "--version" || arg == "-V")
+}
+
+// Take the value following an option
+fn value(iter: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+    iter.next()
+        .ok_or_else(|| format!("Option '{}' expects a value", option))
+}
+
+fn seconds(value: String, option: &str) -> Result<Duration, String> {
+    match value.parse::<u64>() {
+        Ok(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+        _ => Err(format!(
+            "Option '{}' expects a positive number of seconds, got '{}'",
+            option, value
+        )),
+    }
+}
+
+pub fn usage() -> String {
+    let program = env::args()
+        .next()
+        .unwrap_or_else(|| "glide-cli".to_string());
+    format!(
+        "Usage: {} [HOST] [PORT] [OPTIONS]
+
+HOST can be a hostname, an IPv4 address or an IPv6 address (e.g. ::1 or [::1]).
+HOST, PORT and defaults for the options below can be set in {}
+(host, port, username, overwrite, reconnect, verbose, connect_timeout, io_timeout,
+keepalive, transfer_timeout, log_file, output_dir, prompt).
+Saved servers go in [profiles.<name>] tables with host, port and username.
+
+Options:
+    -V, --version            Print the client version and exit
+    -p, --profile <NAME>     Use the host, port and username saved under NAME
+    -u, --username <NAME>    Log in as NAME instead of prompting
+    --overwrite              Replace existing files on receive without asking
+    --open                   Open received files with the default application
+    --script <FILE>          Run commands from FILE ('-' for stdin) instead of prompting
+    --keep-going             Don't stop a script or a multi-file glide at the first failure
+    --dry-run                With --script, check its glide lines without connecting
+    --json                   Print one JSON event per line instead of text
+    -q, --quiet              Only print errors and results, no banners or progress
+    --no-pager               Don't page long 'list' and 'reqs' output (PAGER picks the pager)
+    --reconnect              Re-dial and log back in if the connection drops
+    -v, --verbose            Print diagnostic output to stderr, -vv for protocol details
+    --connect-timeout <SECS> Give up connecting after SECS seconds (default 10)
+    --io-timeout <SECS>      Give up waiting for a server response after SECS seconds (default 30)
+    --keepalive <SECS>       Check the connection every SECS seconds while the prompt is idle
+    --transfer-timeout <SECS>
+                             Abandon a download after SECS seconds without data (default 60)
+    --no-notify              Don't announce new incoming requests at the prompt
+    --log-file <PATH>        Append finished transfers to PATH
+                             (default transfers.log in the platform data directory)
+    --no-log                 Don't log transfers
+    --output-dir <DIR>       Save received files in DIR, creating it if needed
+                             (default the current directory)
+    --prompt <TEMPLATE>      Show TEMPLATE as the prompt, filling in {{user}}, {{host}}
+                             and {{cwd}} (default \"glide@{{user}}> \")
+    --config-dir <DIR>       Keep config.toml, transfers.log and blocked.txt in DIR
+
+{}",
+        program,
+        Paths::resolve(None)
+            .map(|paths| paths.config_file.display().to_string())
+            .unwrap_or_else(|| "config.toml".to_string()),
+        ExitCode::SUMMARY
+    )
+}
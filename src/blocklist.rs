@@ -0,0 +1,80 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// Users whose requests are marked as blocked, kept one username per line so the
+// list survives restarts. The server doesn't know about it, so blocked users can
+// still send requests; they're only flagged in `reqs`.
+pub struct Blocklist {
+    // None when there's nowhere to save it, e.g. without a home directory
+    path: Option<PathBuf>,
+    users: BTreeSet<String>,
+}
+
+impl Blocklist {
+    // A missing file is an empty blocklist
+    pub fn load(path: Option<PathBuf>) -> Blocklist {
+        let users = match path.as_ref().map(fs::read_to_string) {
+            Some(Ok(contents)) => contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => {
+                log::warn!("Unable to read the blocklist: {}", e);
+                BTreeSet::new()
+            }
+            _ => BTreeSet::new(),
+        };
+
+        Blocklist { path, users }
+    }
+
+    pub fn contains(&self, user: &str) -> bool {
+        self.users.contains(user)
+    }
+
+    pub fn users(&self) -> impl Iterator<Item = &String> {
+        self.users.iter()
+    }
+
+    // Returns false if the user was already blocked
+    pub fn block(&mut self, user: &str) -> io::Result<bool> {
+        if !self.users.insert(user.to_string()) {
+            return Ok(false);
+        }
+
+        self.save()?;
+        Ok(true)
+    }
+
+    // Returns false if the user wasn't blocked
+    pub fn unblock(&mut self, user: &str) -> io::Result<bool> {
+        if !self.users.remove(user) {
+            return Ok(false);
+        }
+
+        self.save()?;
+        Ok(true)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut contents = String::new();
+        for user in &self.users {
+            contents.push_str(user);
+            contents.push('\n');
+        }
+
+        fs::write(path, contents)
+    }
+}
@@ -0,0 +1,467 @@
+use crate::sanitize::sanitize_filename;
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use utils::commands::Command;
+use utils::protocol::Transmission;
+use utils::transfers;
+
+// A connection to a glide server
+pub struct Client {
+    stream: TcpStream,
+    // How long to wait for the server to answer a request
+    io_timeout: Duration,
+    // The sender's name for the file of the latest receive, once it's known
+    last_received: Option<String>,
+}
+
+// The server's answer to a username
+#[derive(Debug, PartialEq)]
+pub enum Login {
+    Accepted,
+    Taken,
+    Invalid,
+}
+
+// A file someone wants to send to this user
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct IncomingRequest {
+    pub from: String,
+    pub file: String,
+}
+
+// The connection to the server broke or the server stopped answering, as opposed to
+// the server answering with something the client didn't expect
+#[derive(Debug)]
+pub struct ConnectionError(pub Box<dyn Error>);
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for ConnectionError {}
+
+// What to do when a received file has the same name as an existing one
+pub enum Collision {
+    Overwrite,
+    Rename,
+    Skip,
+}
+
+impl Client {
+    pub async fn connect(
+        address: &str,
+        connect_timeout: Duration,
+        io_timeout: Duration,
+    ) -> Result<Client, Box<dyn Error>> {
+        match tokio::time::timeout(connect_timeout, connect_any(address)).await {
+            Ok(stream) => Ok(Client {
+                stream: stream?,
+                io_timeout,
+                last_received: None,
+            }),
+            Err(_) => Err(format!(
+                "Timed out connecting to {} after {}s",
+                address,
+                connect_timeout.as_secs()
+            )
+            .into()),
+        }
+    }
+
+    pub async fn login(&mut self, username: &str) -> Result<Login, Box<dyn Error>> {
+        self.stream
+            .write_all(
+                Transmission::Username(username.to_string())
+                    .to_bytes()
+                    .as_slice(),
+            )
+            .await
+            .map_err(lost)?;
+
+        match self.read_response().await? {
+            Transmission::UsernameOk => Ok(Login::Accepted),
+            Transmission::UsernameTaken => Ok(Login::Taken),
+            Transmission::UsernameInvalid => Ok(Login::Invalid),
+            response => Err(format!("Unexpected response to login: {:?}", response).into()),
+        }
+    }
+
+    // Send a command and wait for the server's response to it
+    pub async fn request(&mut self, command: Command) -> Result<Transmission, Box<dyn Error>> {
+        log::debug!("Sending command '{}'", command);
+        self.stream
+            .write_all(Transmission::Command(command).to_bytes().as_slice())
+            .await
+            .map_err(lost)?;
+
+        let response = self.read_response().await?;
+        log::debug!("Received {:?}", response);
+        Ok(response)
+    }
+
+    pub async fn list_users(&mut self) -> Result<Vec<String>, Box<dyn Error>> {
+        match self.request(Command::List).await? {
+            Transmission::ConnectedUsers(users) => {
+                Ok(users.iter().map(|user| user.to_string()).collect())
+            }
+            response => Err(format!("Unexpected response to list: {:?}", response).into()),
+        }
+    }
+
+    pub async fn incoming_requests(&mut self) -> Result<Vec<IncomingRequest>, Box<dyn Error>> {
+        match self.request(Command::Requests).await? {
+            Transmission::IncomingRequests(reqs) => Ok(reqs
+                .iter()
+                .map(|req| IncomingRequest {
+                    from: req.sender.to_string(),
+                    file: req.filename.to_string(),
+                })
+                .collect()),
+            response => Err(format!("Unexpected response to reqs: {:?}", response).into()),
+        }
+    }
+
+    // Stream a file to the server once it has accepted the glide request
+    pub async fn send_file(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        transfers::send_file(&mut self.stream, path).await?;
+        Ok(())
+    }
+
+    // Receive the file of an accepted request into a private staging directory and
+    // only move it into `dir` once the transfer completes, so a failed transfer never
+    // leaves a truncated file behind. The transfer is abandoned if no data arrives for
+    // `stall_timeout`. The file keeps the sender's name unless `save_as` is given, and
+    // `on_collision` decides what happens when the name is taken. `on_progress` is
+    // given the bytes received so far and the file's size while it arrives. Returns
+    // `None` if it chose to skip the file.
+    pub async fn receive_file(
+        &mut self,
+        dir: &Path,
+        save_as: Option<&str>,
+        stall_timeout: Duration,
+        on_collision: impl FnOnce(&str) -> io::Result<Collision>,
+        mut on_progress: impl FnMut(u64, u64),
+    ) -> Result<Option<PathBuf>, Box<dyn Error>> {
+        // Creating the staging directory also creates a missing output directory
+        let staging = Staging(dir.join(format!(".glide-partial-{}", std::process::id())));
+        tokio::fs::create_dir_all(&staging.0)
+            .await
+            .map_err(|e| format!("Unable to create {}: {}", dir.display(), e))?;
+
+        let staging_dir = staging.0.to_string_lossy().to_string();
+        log::debug!("Receiving into {}", staging_dir);
+        self.last_received = None;
+        let stream = &mut self.stream;
+        let last_received = &mut self.last_received;
+        // The size the sender announced, once the metadata has been read
+        let total = Cell::new(None);
+        let receive = async {
+            // utils creates the file from the sender's name as it is, so the transfer
+            // is refused before it runs unless the name is known to be safe
+            let announced = peek_transfer(stream).await?;
+            *last_received = Some(announced.name.clone());
+            total.set(Some(announced.size));
+            check_sender_name(&announced.name)?;
+            if let Some(chunk_name) = &announced.chunk_name {
+                if *chunk_name != announced.name {
+                    return Err(format!(
+                        "Refusing the transfer, its data is for '{}' but the file is '{}'",
+                        chunk_name, announced.name
+                    )
+                    .into());
+                }
+            }
+
+            if announced.size == 0 {
+                // No chunk follows an empty file, so there's nothing for utils to wait
+                // on: the metadata is taken off the stream and the file made here
+                let mut metadata = vec![0; announced.metadata_len];
+                stream.read_exact(&mut metadata).await.map_err(lost)?;
+                tokio::fs::File::create(staging.0.join(&announced.name)).await?;
+            } else {
+                transfers::receive_file(stream, &staging_dir).await?;
+            }
+            Ok::<_, Box<dyn Error>>(announced)
+        };
+        let announced = tokio::select! {
+            result = receive => result?,
+            _ = stalled(&staging.0, stall_timeout, &total, &mut on_progress) => {
+                return Err(format!(
+                    "No data arrived for {}s, the transfer was abandoned",
+                    stall_timeout.as_secs()
+                )
+                .into());
+            }
+        };
+
+        let mut entries = tokio::fs::read_dir(&staging.0).await?;
+        let Some(entry) = entries.next_entry().await? else {
+            return Err("No file arrived in the output directory".into());
+        };
+        if !entry.file_type().await?.is_file() {
+            return Err(format!(
+                "'{}' arrived as something other than a file",
+                entry.file_name().to_string_lossy()
+            )
+            .into());
+        }
+
+        // utils may return as if it finished when the connection closes midway
+        let size = entry.metadata().await?.len();
+        if size < announced.size {
+            return Err(format!(
+                "The connection closed after {} of {} bytes",
+                size, announced.size
+            )
+            .into());
+        }
+
+        let name = match save_as {
+            Some(name) => sanitize_filename(name)?,
+            None => entry.file_name().to_string_lossy().to_string(),
+        };
+
+        let mut target = dir.join(&name);
+        if target.exists() {
+            match on_collision(&name)? {
+                Collision::Overwrite => {}
+                Collision::Rename => target = next_free_name(&target),
+                Collision::Skip => return Ok(None),
+            }
+        }
+
+        tokio::fs::rename(entry.path(), &target).await?;
+
+        Ok(Some(target))
+    }
+
+    // The sender's name for the file of the latest `receive_file`, which is there even
+    // if it failed or was cancelled once the name had arrived
+    pub fn last_received(&self) -> Option<&str> {
+        self.last_received.as_deref()
+    }
+
+    // Tell the server the client is leaving
+    pub async fn disconnect(&mut self) -> Result<(), Box<dyn Error>> {
+        self.stream
+            .write_all(Transmission::ClientDisconnected.to_bytes().as_slice())
+            .await
+            .map_err(lost)?;
+        Ok(())
+    }
+
+    // Read the next transmission, giving up if the server stays silent for too long
+    async fn read_response(&mut self) -> Result<Transmission, Box<dyn Error>> {
+        match tokio::time::timeout(self.io_timeout, Transmission::from_stream(&mut self.stream))
+            .await
+        {
+            Ok(response) => response.map_err(lost),
+            Err(_) => Err(lost(format!(
+                "Server didn't respond within {}s",
+                self.io_timeout.as_secs()
+            ))),
+        }
+    }
+}
+
+fn lost(error: impl Into<Box<dyn Error>>) -> Box<dyn Error> {
+    Box::new(ConnectionError(error.into()))
+}
+
+// A hostname may resolve to several addresses, try each in order until one connects
+async fn connect_any(address: &str) -> io::Result<TcpStream> {
+    let mut last_error = None;
+
+    for addr in tokio::net::lookup_host(address).await? {
+        log::debug!("Trying {}", addr);
+        match TcpStream::connect(addr).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("'{}' didn't resolve to any address", address),
+        )
+    }))
+}
+
+// What the opening frames of a transfer say about the file that follows
+struct Announced {
+    name: String,
+    size: u64,
+    // How many bytes the metadata frame takes up on the stream
+    metadata_len: usize,
+    // The name the first chunk is for, None for an empty file
+    chunk_name: Option<String>,
+}
+
+// A sender's file name has to be a bare name: not absolute, and one that sanitizing
+// leaves alone, so it has no directories or `..` in it
+fn check_sender_name(name: &str) -> Result<(), String> {
+    if Path::new(name).is_absolute() || sanitize_filename(name)? != name {
+        return Err(format!(
+            "Refusing the sender's file name '{}', it isn't a bare file name",
+            name
+        ));
+    }
+    Ok(())
+}
+
+// Read the opening of a transfer ahead of utils without taking it off the stream:
+// the metadata frame (code 5, the file name up to a NUL, then the size as 4 bytes BE)
+// and, unless the file is empty, the first chunk frame up to its file name (code 6,
+// the name up to a NUL). Gives up if they stop arriving before they're complete.
+async fn peek_transfer(stream: &TcpStream) -> Result<Announced, Box<dyn Error>> {
+    let mut buf = vec![0; 4096];
+    let mut seen = 0;
+    let mut last_growth = tokio::time::Instant::now();
+
+    loop {
+        let len = stream.peek(&mut buf).await.map_err(lost)?;
+        if len == 0 {
+            return Err(lost("The connection closed before the file arrived"));
+        }
+        if let Some(announced) = parse_opening(&buf[..len])? {
+            return Ok(announced);
+        }
+
+        // A peek returns straight away while anything is buffered, so more of the
+        // opening is polled for rather than waited on
+        if len == buf.len() {
+            return Err("The sender's file name is too long".into());
+        }
+        if len > seen {
+            seen = len;
+            last_growth = tokio::time::Instant::now();
+        } else if last_growth.elapsed() >= Duration::from_secs(1) {
+            return Err("The sender's file metadata never arrived complete".into());
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+// Returns None while more of the opening has yet to arrive
+fn parse_opening(buf: &[u8]) -> Result<Option<Announced>, String> {
+    if buf[0] != 5 {
+        return Err(format!(
+            "Expected file metadata, got a frame with code {}",
+            buf[0]
+        ));
+    }
+    let Some(name_len) = buf[1..].iter().position(|&b| b == 0) else {
+        return Ok(None);
+    };
+    let Some(size) = buf.get(name_len + 2..name_len + 6) else {
+        return Ok(None);
+    };
+    let name = String::from_utf8_lossy(&buf[1..name_len + 1]).to_string();
+    let size = u32::from_be_bytes(size.try_into().unwrap()) as u64;
+    if size == 0 {
+        return Ok(Some(Announced {
+            name,
+            size,
+            metadata_len: name_len + 6,
+            chunk_name: None,
+        }));
+    }
+
+    let chunk = &buf[name_len + 6..];
+    match chunk.first() {
+        None => return Ok(None),
+        Some(6) => {}
+        Some(code) => {
+            return Err(format!(
+                "Expected file data, got a frame with code {}",
+                code
+            ))
+        }
+    }
+    let Some(chunk_name_len) = chunk[1..].iter().position(|&b| b == 0) else {
+        return Ok(None);
+    };
+
+    Ok(Some(Announced {
+        name,
+        size,
+        metadata_len: name_len + 6,
+        chunk_name: Some(String::from_utf8_lossy(&chunk[1..chunk_name_len + 1]).to_string()),
+    }))
+}
+
+// utils writes straight into the staging directory without reporting progress, so
+// progress is how much it holds, passed on to `on_progress` once the size of the
+// whole file is known. Resolves once that stops growing for `timeout`.
+async fn stalled(
+    staging: &Path,
+    timeout: Duration,
+    total: &Cell<Option<u64>>,
+    on_progress: &mut impl FnMut(u64, u64),
+) {
+    let mut last_size = 0;
+    let mut last_progress = tokio::time::Instant::now();
+    let mut ticker = tokio::time::interval(Duration::from_millis(250));
+
+    loop {
+        ticker.tick().await;
+
+        let size = staged_size(staging);
+        if let Some(total) = total.get() {
+            on_progress(size, total);
+        }
+        if size != last_size {
+            last_size = size;
+            last_progress = tokio::time::Instant::now();
+        } else if last_progress.elapsed() >= timeout {
+            return;
+        }
+    }
+}
+
+fn staged_size(staging: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(staging) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(|entry| entry.ok()?.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+// Removed however a receive ends, including when it's dropped midway by a cancel,
+// so nothing is ever left in staging
+struct Staging(PathBuf);
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// Find the first free "name (n).ext" next to `path`
+fn next_free_name(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
@@ -0,0 +1,193 @@
+use crate::exit::ExitCode;
+
+// Every command the client understands, in the order `help` lists them.
+// Aliases are resolved by `normalize` and listed by `help`.
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "glide",
+        aliases: &["send"],
+        usage: "glide [--dry-run] <path>... @<user>",
+        summary: "Send a file to another user",
+        details: "Asks @<user> to accept the file at <path>. The file is streamed once the
+server has registered the request. Quote paths with spaces in them, e.g.
+    glide \"my file.txt\" @<user>
+
+With several paths each file is sent as its own request, one after the other.
+A failed file stops the rest unless --keep-going was given.
+
+With --dry-run the files and recipient are checked and what would be sent is
+printed, without sending anything to the server. Starting the client with
+--dry-run and --script checks every glide in the script without connecting.
+
+Use - as the path to send standard input instead, naming the file with --name:
+    glide - @<user> --name <name>",
+    },
+    CommandInfo {
+        name: "reqs",
+        aliases: &["requests"],
+        usage: "reqs",
+        summary: "List incoming file requests",
+        details: "Shows who wants to send you a file and the name of that file.
+New requests are also announced at the prompt unless --no-notify was given.",
+    },
+    CommandInfo {
+        name: "ok",
+        aliases: &["accept"],
+        usage: "ok @<user>|#<n> [as <name>]",
+        summary: "Accept a file request",
+        details: "Accepts the pending request from @<user>, or the <n>th one listed by reqs, and
+downloads the file into the output directory (the current directory unless
+--output-dir was given).
+With as <name> the file is saved as <name> instead of the sender's file name.",
+    },
+    CommandInfo {
+        name: "no",
+        aliases: &["decline"],
+        usage: "no @<user>|#<n>",
+        summary: "Decline a file request",
+        details: "Declines the pending request from @<user>, or the <n>th one listed by reqs.",
+    },
+    CommandInfo {
+        name: "block",
+        aliases: &[],
+        usage: "block @<user>",
+        summary: "Mark requests from a user as blocked",
+        details: "Adds @<user> to your blocklist, so their requests are marked as blocked in
+'reqs'. The blocklist is kept on this machine only, so the server still delivers
+their requests.",
+    },
+    CommandInfo {
+        name: "unblock",
+        aliases: &[],
+        usage: "unblock @<user>",
+        summary: "Remove a user from the blocklist",
+        details: "Removes @<user> from your blocklist.",
+    },
+    CommandInfo {
+        name: "blocks",
+        aliases: &[],
+        usage: "blocks",
+        summary: "List blocked users",
+        details: "Shows every user on your blocklist.",
+    },
+    CommandInfo {
+        name: "list",
+        aliases: &["ls"],
+        usage: "list",
+        summary: "List connected users",
+        details: "Shows every user currently connected to the server.",
+    },
+    CommandInfo {
+        name: "whoami",
+        aliases: &[],
+        usage: "whoami",
+        summary: "Show the username you are connected as",
+        details: "Prints the username the server accepted for this session.",
+    },
+    CommandInfo {
+        name: "ping",
+        aliases: &[],
+        usage: "ping [count]",
+        summary: "Measure the round trip to the server",
+        details: "Sends <count> requests (1 by default) and prints how long each reply took,
+with min/avg/max when there is more than one.",
+    },
+    CommandInfo {
+        name: "version",
+        aliases: &[],
+        usage: "version",
+        summary: "Show the client version",
+        details: "Prints the version of this client. The server doesn't report a version.",
+    },
+    CommandInfo {
+        name: "help",
+        aliases: &["?"],
+        usage: "help [command]",
+        summary: "Show this help",
+        details: "Without arguments, lists every command. With a command name, shows how to
+use that command.",
+    },
+    CommandInfo {
+        name: "exit",
+        aliases: &["quit", "q"],
+        usage: "exit",
+        summary: "Disconnect and quit",
+        details: "Tells the server you are leaving and closes the client.",
+    },
+];
+
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub usage: &'static str,
+    pub summary: &'static str,
+    pub details: &'static str,
+}
+
+// Find a command by its name or one of its aliases
+pub fn lookup(word: &str) -> Option<&'static CommandInfo> {
+    COMMANDS
+        .iter()
+        .find(|cmd| cmd.name == word || cmd.aliases.contains(&word))
+}
+
+// Lowercase the command word and resolve aliases, leaving the arguments untouched
+// since usernames and paths are case-sensitive
+pub fn normalize(input: &str) -> String {
+    let input = input.trim();
+    let (word, rest) = match input.find(char::is_whitespace) {
+        Some(end) => input.split_at(end),
+        None => (input, ""),
+    };
+
+    let word = word.to_lowercase();
+    let name = lookup(&word).map_or(word.as_str(), |cmd| cmd.name);
+
+    format!("{}{}", name, rest)
+}
+
+// The command overview, or the detailed usage of one command. None if the command is
+// unknown.
+pub fn help_text(topic: Option<&str>) -> Option<String> {
+    let Some(topic) = topic else {
+        let width = COMMANDS
+            .iter()
+            .map(|cmd| cmd.usage.len())
+            .max()
+            .unwrap_or(0);
+
+        let mut text = "Available commands:\n".to_string();
+        for cmd in COMMANDS {
+            text.push_str(&format!(
+                "    {:width$}    {}{}\n",
+                cmd.usage,
+                cmd.summary,
+                format_aliases(cmd, " (", ")"),
+                width = width
+            ));
+        }
+        text.push_str("\nCommands are case-insensitive and the @ before a username is optional.\n");
+        text.push_str("Use 'help <command>' for details.\n");
+        text.push_str(&format!("\n{}", ExitCode::SUMMARY));
+
+        return Some(text);
+    };
+
+    let cmd = lookup(&topic.to_lowercase())?;
+
+    Some(format!(
+        "Usage: {}\n\n{}\n\n{}{}",
+        cmd.usage,
+        cmd.summary,
+        cmd.details,
+        format_aliases(cmd, "\n\n", "")
+    ))
+}
+
+fn format_aliases(cmd: &CommandInfo, before: &str, after: &str) -> String {
+    match cmd.aliases.len() {
+        0 => String::new(),
+        1 => format!("{}alias: {}{}", before, cmd.aliases[0], after),
+        _ => format!("{}aliases: {}{}", before, cmd.aliases.join(", "), after),
+    }
+}
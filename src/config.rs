@@ -0,0 +1,77 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Defaults read from config.toml, overridden by command-line arguments
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub overwrite: Option<bool>,
+    pub reconnect: Option<bool>,
+    pub verbose: Option<bool>,
+    pub connect_timeout: Option<u64>,
+    pub io_timeout: Option<u64>,
+    pub keepalive: Option<u64>,
+    pub transfer_timeout: Option<u64>,
+    pub log_file: Option<PathBuf>,
+    pub output_dir: Option<PathBuf>,
+    pub prompt: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+// A named server from a [profiles.<name>] table
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+}
+
+impl Config {
+    // A missing config file is the same as an empty one
+    pub fn load(path: Option<&Path>) -> Result<Config, String> {
+        let Some(path) = path else {
+            return Ok(Config::default());
+        };
+
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(format!("Unable to read {}: {}", path.display(), e)),
+        };
+
+        toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config file {}\n{}", path.display(), e))
+    }
+
+    // Overlay a named profile on top of the top-level values
+    pub fn select_profile(mut self, name: &str) -> Result<Config, String> {
+        let Some(profile) = self.profiles.remove(name) else {
+            if self.profiles.is_empty() {
+                return Err(format!(
+                    "Profile '{}' not found, the config file defines no profiles",
+                    name
+                ));
+            }
+
+            let available: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            return Err(format!(
+                "Profile '{}' not found. Available profiles: {}",
+                name,
+                available.join(", ")
+            ));
+        };
+
+        self.host = profile.host.or(self.host);
+        self.port = profile.port.or(self.port);
+        self.username = profile.username.or(self.username);
+
+        Ok(self)
+    }
+}
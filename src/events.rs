@@ -0,0 +1,80 @@
+use serde::Serialize;
+
+// Machine-readable events, printed one JSON object per line under --json
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Connected {
+        address: String,
+    },
+    LoggedIn {
+        username: String,
+    },
+    Whoami {
+        username: String,
+    },
+    Version {
+        version: String,
+    },
+    Help {
+        text: String,
+    },
+    Users {
+        users: Vec<String>,
+    },
+    Ping {
+        replies_ms: Vec<f64>,
+    },
+    Requests {
+        requests: Vec<RequestEntry>,
+    },
+    IncomingRequest {
+        from: String,
+        file: String,
+    },
+    Declined {
+        from: String,
+    },
+    Blocked {
+        users: Vec<String>,
+    },
+    DryRun {
+        file: String,
+        to: String,
+        size: u64,
+    },
+    Skipped {
+        file: String,
+    },
+    Cancelling,
+    TransferComplete {
+        direction: Direction,
+        file: String,
+        size: u64,
+        seconds: f64,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Serialize)]
+pub struct RequestEntry {
+    // The request's place in the listing, as used by `ok #<n>`
+    pub number: usize,
+    pub from: String,
+    pub file: String,
+    pub blocked: bool,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Upload,
+    Download,
+}
+
+pub fn emit(event: &Event) {
+    // Events only hold strings, numbers and lists, so serializing can't fail
+    println!("{}", serde_json::to_string(event).unwrap());
+}
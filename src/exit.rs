@@ -0,0 +1,54 @@
+use glide_cli::ConnectionError;
+use std::error::Error;
+use std::fmt;
+
+// Process exit codes, so scripts can tell failures apart
+#[derive(Clone, Copy, Debug)]
+pub enum ExitCode {
+    Success = 0,
+    Generic = 1,
+    Connection = 2,
+    Auth = 3,
+    Transfer = 4,
+}
+
+impl ExitCode {
+    pub const SUMMARY: &'static str = "Exit codes:
+    0    Success
+    1    Invalid arguments, config or command
+    2    Connecting to the server failed or the connection was lost
+    3    Logging in failed
+    4    Sending or receiving a file failed";
+
+    pub fn for_error(error: &(dyn Error + 'static)) -> ExitCode {
+        if error.is::<TransferError>() {
+            ExitCode::Transfer
+        } else if error.is::<ConnectionError>() {
+            ExitCode::Connection
+        } else {
+            ExitCode::Generic
+        }
+    }
+}
+
+impl From<ExitCode> for std::process::ExitCode {
+    fn from(code: ExitCode) -> Self {
+        std::process::ExitCode::from(code as u8)
+    }
+}
+
+// An error that happened while a file was being sent or received
+#[derive(Debug)]
+pub struct TransferError(pub Box<dyn Error>);
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Transfer failed: {}", self.0)
+    }
+}
+
+impl Error for TransferError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.0.as_ref())
+    }
+}
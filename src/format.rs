@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+const UNITS: [&str; 5] = ["KB", "MB", "GB", "TB", "PB"];
+
+// Render a byte count with binary units, e.g. 1536 as "1.5 KB"
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+// Summarize a finished transfer, e.g. "512.0 MB in 8.3s (61.7 MB/s)"
+pub fn format_speed(bytes: u64, elapsed: Duration) -> String {
+    // A transfer too quick to time would otherwise divide by zero
+    let secs = elapsed.as_secs_f64().max(0.001);
+    format!(
+        "{} in {:.1}s ({}/s)",
+        format_bytes(bytes),
+        secs,
+        format_bytes((bytes as f64 / secs) as u64)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_switch_units_at_1024() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.0 KB");
+        assert_eq!(format_bytes(1025), "1.0 KB");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(1048576), "1.0 MB");
+    }
+
+    #[test]
+    fn speed_of_an_instant_transfer_is_finite() {
+        assert_eq!(
+            format_speed(1024, Duration::ZERO),
+            "1.0 KB in 0.0s (1000.0 KB/s)"
+        );
+        assert_eq!(format_speed(0, Duration::ZERO), "0 B in 0.0s (0 B/s)");
+    }
+}
@@ -0,0 +1,82 @@
+use crate::events::{self, Event};
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+// A second Ctrl+C within this window force-quits instead of cancelling
+const FORCE_QUIT_WINDOW: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Transfer cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+// Tracks whether a transfer is in flight so Ctrl+C can cancel it instead of
+// killing the client
+#[derive(Clone)]
+pub struct Interrupt {
+    transfer_active: Arc<AtomicBool>,
+    cancel: Arc<Notify>,
+}
+
+impl Interrupt {
+    // `json` reports a cancel as an event instead of a message
+    pub fn install(json: bool) -> Interrupt {
+        let interrupt = Interrupt {
+            transfer_active: Arc::new(AtomicBool::new(false)),
+            cancel: Arc::new(Notify::new()),
+        };
+
+        let handler = interrupt.clone();
+        tokio::spawn(async move {
+            let mut last_press: Option<Instant> = None;
+
+            while tokio::signal::ctrl_c().await.is_ok() {
+                let forced = last_press.is_some_and(|at| at.elapsed() < FORCE_QUIT_WINDOW);
+                if forced || !handler.transfer_active.load(Ordering::SeqCst) {
+                    // Nothing to cancel, behave like a regular Ctrl+C
+                    std::process::exit(130);
+                }
+
+                if json {
+                    events::emit(&Event::Cancelling);
+                } else {
+                    println!("\nCancelling transfer... press Ctrl+C again to force quit");
+                }
+                handler.cancel.notify_waiters();
+                last_press = Some(Instant::now());
+            }
+        });
+
+        interrupt
+    }
+
+    // Run a transfer, failing with `Cancelled` if Ctrl+C is pressed before it finishes
+    pub async fn guard<T, E, F>(&self, transfer: F) -> Result<T, Box<dyn Error>>
+    where
+        F: Future<Output = Result<T, E>>,
+        E: Into<Box<dyn Error>>,
+    {
+        // Register for the notification before the handler can see the transfer
+        let cancelled = self.cancel.notified();
+        self.transfer_active.store(true, Ordering::SeqCst);
+
+        let result = tokio::select! {
+            result = transfer => result.map_err(Into::into),
+            _ = cancelled => Err(Cancelled.into()),
+        };
+
+        self.transfer_active.store(false, Ordering::SeqCst);
+        result
+    }
+}
@@ -0,0 +1,64 @@
+use crate::stdin::StdinLines;
+use glide_cli::{Client, IncomingRequest};
+use std::collections::HashSet;
+use std::error::Error;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// Reads lines from stdin while checking on the connection every `interval`, so a
+// connection dropped while the prompt sits idle is noticed before the next command.
+// Heartbeats only go out between commands, never while a transfer owns the stream.
+pub struct KeepaliveStdin {
+    interval: Duration,
+    // Set when heartbeats also look for new incoming requests
+    notify: bool,
+    // Requests already announced, so each is announced once for as long as it's pending
+    announced: HashSet<IncomingRequest>,
+}
+
+impl KeepaliveStdin {
+    pub fn new(interval: Duration, notify: bool) -> KeepaliveStdin {
+        KeepaliveStdin {
+            interval,
+            notify,
+            announced: HashSet::new(),
+        }
+    }
+
+    // Returns an empty line at EOF, like `read_line`. `on_request` is called for each
+    // request that arrives while waiting. A line still being typed when a heartbeat
+    // fails stays with `stdin` for its next reader.
+    pub async fn read_line(
+        &mut self,
+        stdin: &mut StdinLines,
+        client: &mut Client,
+        mut on_request: impl FnMut(&IncomingRequest),
+    ) -> Result<String, Box<dyn Error>> {
+        let mut ticker = tokio::time::interval_at(Instant::now() + self.interval, self.interval);
+        let line = stdin.read_line();
+        tokio::pin!(line);
+
+        loop {
+            tokio::select! {
+                line = &mut line => return Ok(line?),
+                // There's no dedicated keepalive frame and the server never pushes
+                // anything, so a `reqs` or `list` request stands in for one. No reply
+                // within the client's I/O timeout is an error.
+                _ = ticker.tick() => {
+                    log::debug!("Sending heartbeat");
+                    if !self.notify {
+                        client.list_users().await?;
+                        continue;
+                    }
+
+                    let requests: HashSet<IncomingRequest> =
+                        client.incoming_requests().await?.into_iter().collect();
+                    for request in requests.difference(&self.announced) {
+                        on_request(request);
+                    }
+                    self.announced = requests;
+                }
+            }
+        }
+    }
+}
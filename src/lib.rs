@@ -0,0 +1,9 @@
+// The glide client as a library: connecting, logging in, requests and transfers.
+// The binary is a command-line front end for `Client`, and everything that talks to
+// the terminal stays there.
+mod client;
+pub mod sanitize;
+pub mod tokenize;
+pub mod username;
+
+pub use client::{Client, Collision, ConnectionError, IncomingRequest, Login};
@@ -1,184 +1,1461 @@
+mod args;
+mod blocklist;
+mod commands;
+mod config;
+mod events;
+mod exit;
+mod format;
+mod interrupt;
+mod keepalive;
+mod open;
+mod pager;
+mod paths;
+mod progress;
+mod spinner;
+mod stdin;
+mod transfer_log;
+mod user_list;
+
+use args::Args;
+use blocklist::Blocklist;
+use config::Config;
+use crossterm::event::KeyCode;
+use crossterm::terminal::{Clear, ClearType};
+use events::{Direction, Event, RequestEntry};
+use exit::{ExitCode, TransferError};
+use format::{format_bytes, format_speed};
+use glide_cli::tokenize::{quote, tokenize};
+use glide_cli::username::validate_username;
+use glide_cli::{Client, Collision, IncomingRequest, Login};
+use interrupt::{Cancelled, Interrupt};
+use keepalive::KeepaliveStdin;
+use paths::Paths;
+use progress::Progress;
 use regex::Regex;
-use std::env;
+use spinner::Spinner;
+use std::ffi::OsStr;
 use std::io::Write;
-use std::io::{self, BufRead};
-use std::path::Path;
-use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
+use std::io::{self, BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use stdin::StdinLines;
+use transfer_log::Status;
 use utils::commands::Command;
 use utils::protocol::Transmission;
-use utils::transfers;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+// How often to look for new requests at the prompt when --keepalive isn't given
+const NOTIFY_INTERVAL: Duration = Duration::from_secs(10);
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Retrieve command-line arguments
-    let args: Vec<String> = env::args().collect();
+async fn main() -> std::process::ExitCode {
+    pager::restore_terminal_on_panic();
+
+    match run().await {
+        Ok(code) => code.into(),
+        Err(e) => {
+            pager::restore_terminal();
+            eprintln!("Error: {}", e);
+            ExitCode::for_error(e.as_ref()).into()
+        }
+    }
+}
+
+async fn run() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    if args::version_requested() {
+        println!("glide-cli {}", VERSION);
+        return Ok(ExitCode::Success);
+    }
+
+    let paths = match args::config_dir() {
+        Ok(config_dir) => Paths::resolve(config_dir),
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, args::usage());
+            return Ok(ExitCode::Generic);
+        }
+    };
 
-    // Check if the required arguments are provided
-    if args.len() != 3 {
-        eprintln!("Usage: {} <IP> <PORT>", args[0]);
-        std::process::exit(1);
+    let config = match Config::load(paths.as_ref().map(|paths| paths.config_file.as_path())) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(ExitCode::Generic);
+        }
+    };
+
+    // Retrieve and check command-line arguments
+    let args = match Args::parse(config, paths.as_ref()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, args::usage());
+            return Ok(ExitCode::Generic);
+        }
+    };
+
+    init_logging(args.verbosity);
+
+    // A dry run has nothing to ask the server
+    if args.dry_run {
+        return dry_run_script(&args);
     }
 
-    // Parse the IP and port from arguments
-    let ip = &args[1];
-    let port = &args[2];
-    let address = format!("{}:{}", ip, port);
+    let address = format_address(&args.host, &args.port);
 
     // Connect to the server
-    let mut stream = TcpStream::connect(&address).await?;
-    println!("Connected to server at {}!", address);
+    let spinner = Spinner::start(&format!("Connecting to {}...", address), args.quiet);
+    let connected = Client::connect(&address, args.connect_timeout, args.io_timeout).await;
+    spinner.stop().await;
+
+    let mut client = match connected {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Unable to connect to {}: {}", address, e);
+            return Ok(ExitCode::Connection);
+        }
+    };
+    if args.json {
+        events::emit(&Event::Connected {
+            address: address.clone(),
+        });
+    } else if !args.quiet {
+        println!("Connected to server at {}!", address);
+    }
+
+    // Every read of a line from the terminal goes through here, so a read given up on
+    // by one reader can't swallow the next line meant for another
+    let mut stdin = StdinLines::spawn();
+
+    let Some(username) = login(&mut client, args.username.clone(), &mut stdin, &args).await? else {
+        return Ok(ExitCode::Auth);
+    };
+    let mut session = Session {
+        username,
+        blocklist: Blocklist::load(paths.as_ref().map(|paths| paths.blocklist.clone())),
+    };
 
-    let _username = login(&mut stream).await?;
+    let interrupt = Interrupt::install(args.json);
+
+    // Commands come from the terminal unless a script was given
+    let mut script: Option<Box<dyn BufRead>> = match args.script.as_deref() {
+        Some("-") => Some(Box::new(io::BufReader::new(io::stdin()))),
+        Some(path) => Some(Box::new(io::BufReader::new(std::fs::File::open(path)?))),
+        None => None,
+    };
+
+    // Notifications ride on the heartbeat, so they turn it on even without --keepalive,
+    // and an unanswered poll ends the session just as a heartbeat would
+    let notify = args.notify && script.is_none();
+    let mut keepalive = match args.keepalive {
+        Some(interval) => Some(KeepaliveStdin::new(interval, notify)),
+        None if notify => Some(KeepaliveStdin::new(NOTIFY_INTERVAL, true)),
+        None => None,
+    };
 
     // Command loop
-    let stdin = io::stdin();
     let mut input = String::new();
+    let mut exit_code = ExitCode::Success;
+    // A script that keeps going past a failure still exits with the first one
+    let mut first_failure = None;
 
-    println!("Type 'help' to see available commands.");
+    // Only a person at the terminal needs the prompt and banners
+    let interactive = script.is_none() && !args.json;
+
+    if interactive && !args.quiet {
+        println!("Type 'help' to see available commands.");
+    }
 
     loop {
-        // Get user input
+        // Get the next command
         input.clear();
-        print!("glide> ");
-        io::stdout().flush()?;
-        stdin.lock().read_line(&mut input)?;
+        let read = match script.as_mut() {
+            Some(reader) => reader.read_line(&mut input)?,
+            None => {
+                if interactive {
+                    print!("{}", prompt(&session, &args));
+                    io::stdout().flush()?;
+                }
 
-        let input = input.trim();
-        if input == "exit" {
-            println!("Thank you for using Glide. Goodbye!");
-            stream
-                .write_all(Transmission::ClientDisconnected.to_bytes().as_slice())
-                .await?;
+                let read = match keepalive.as_mut() {
+                    Some(keepalive) => Some(
+                        keepalive
+                            .read_line(&mut stdin, &mut client, |request| {
+                                announce_request(request, &session, &args, interactive)
+                            })
+                            .await,
+                    ),
+                    None => None,
+                };
+
+                match read {
+                    Some(Ok(line)) => {
+                        input.push_str(&line);
+                        line.len()
+                    }
+                    Some(Err(e)) => {
+                        fail(
+                            &args,
+                            ExitCode::Connection,
+                            format!("Connection lost: {}", e),
+                        );
+                        if !args.reconnect {
+                            exit_code = ExitCode::Connection;
+                            break;
+                        }
+
+                        let Some(resumed) =
+                            resume_session(&address, session.username.clone(), &mut stdin, &args)
+                                .await?
+                        else {
+                            return Ok(ExitCode::Auth);
+                        };
+                        (client, session.username) = resumed;
+                        continue;
+                    }
+                    None => {
+                        let line = stdin.read_line().await?;
+                        input.push_str(&line);
+                        line.len()
+                    }
+                }
+            }
+        };
+
+        let input = commands::normalize(input.trim());
+        if read == 0 || input == "exit" {
+            if interactive && !args.quiet {
+                println!("Thank you for using Glide. Goodbye!");
+            }
+            client.disconnect().await?;
             break;
         }
 
-        // Parse the command
-        let command = Command::parse(input);
-
-        if !validate_command(&command.to_string()) {
-            println!("Invalid command '{}'. Use 'help' to see more", input);
+        // Scripts may contain blank lines and comments
+        if script.is_some() && (input.is_empty() || input.starts_with('#')) {
             continue;
         }
 
-        // Validate glide command
-        if let Command::Glide { path, to: _ } = &command {
-            // Check if file exists
-            if Path::new(&path).try_exists().is_err() || !Path::new(&path).is_file() {
-                println!("Path '{}' is invalid. File does not exist", path);
-                continue;
+        let outcome = match run_command(&mut client, &input, &mut session, &args, &interrupt).await
+        {
+            Ok(outcome) => outcome,
+            // After an error midway through a command the protocol state is unknown,
+            // so the only way to carry on is a fresh connection
+            Err(e) if args.reconnect => {
+                fail(
+                    &args,
+                    ExitCode::Connection,
+                    format!(
+                        "Connection lost: {}. Any transfer in progress has failed.",
+                        e
+                    ),
+                );
+                match ExitCode::for_error(e.as_ref()) {
+                    ExitCode::Transfer => Outcome::SessionEnded(ExitCode::Transfer),
+                    _ => Outcome::SessionEnded(ExitCode::Connection),
+                }
             }
-        }
-
-        // Send command to the server
-        stream
-            .write_all(Transmission::Command(command.clone()).to_bytes().as_slice())
-            .await?;
-        let response = Transmission::from_stream(&mut stream).await?;
+            Err(e) => return Err(e),
+        };
 
-        match command {
-            Command::Glide { path, to: _ } => {
-                if matches!(response, Transmission::GlideRequestSent) {
-                    transfers::send_file(&mut stream, &path).await?;
-                } else if matches!(response, Transmission::UsernameInvalid) {
-                    println!("Unable to send glide request, username invalid");
-                } else {
-                    println!("Unable to send glide request\n{:#?}", response);
+        match outcome {
+            Outcome::Success => {}
+            Outcome::Failure(code) => {
+                if script.is_some() && !args.keep_going {
+                    exit_code = code;
+                    client.disconnect().await?;
+                    break;
+                }
+                if script.is_some() {
+                    first_failure.get_or_insert(code);
                 }
             }
-            Command::Ok(_) => {
-                if matches!(response, Transmission::OkSuccess) {
-                    transfers::receive_file(&mut stream, ".").await?;
-                } else {
-                    println!("`ok` command failed! Invalid request")
+            Outcome::SessionEnded(code) => {
+                if !args.reconnect || (script.is_some() && !args.keep_going) {
+                    exit_code = code;
+                    break;
+                }
+                if script.is_some() {
+                    first_failure.get_or_insert(code);
                 }
+
+                let Some(resumed) =
+                    resume_session(&address, session.username.clone(), &mut stdin, &args).await?
+                else {
+                    return Ok(ExitCode::Auth);
+                };
+                (client, session.username) = resumed;
             }
-            Command::List => {
-                let Transmission::ConnectedUsers(users) = response else {
-                    println!("Command failed\n{:#?}", response);
-                    return Ok(());
+            // Whoever cancelled wants the prompt back, not the client gone, so this
+            // resumes even without --reconnect
+            Outcome::Cancelled => {
+                let Some(resumed) =
+                    resume_session(&address, session.username.clone(), &mut stdin, &args).await?
+                else {
+                    return Ok(ExitCode::Auth);
                 };
+                (client, session.username) = resumed;
+            }
+        }
+    }
 
-                println!("Connected users:");
-                for user in users.iter() {
-                    println!(" @{}", user);
-                }
+    Ok(first_failure.unwrap_or(exit_code))
+}
+
+// Diagnostics go to stderr: warnings by default, more detail with -v and -vv
+fn init_logging(verbosity: u8) {
+    let level = match verbosity {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Info,
+        _ => log::LevelFilter::Debug,
+    };
+
+    env_logger::Builder::new()
+        .filter_level(level)
+        .format_timestamp(None)
+        .format_target(false)
+        .init();
+}
+
+// What the client keeps about the logged-in session
+struct Session {
+    username: String,
+    blocklist: Blocklist,
+}
+
+enum Outcome {
+    Success,
+    Failure(ExitCode),
+    // The connection can no longer be used
+    SessionEnded(ExitCode),
+    // A transfer was cancelled at the prompt, leaving the connection unusable
+    Cancelled,
+}
+
+// Run a single command, either locally or through the server
+async fn run_command(
+    client: &mut Client,
+    input: &str,
+    session: &mut Session,
+    args: &Args,
+    interrupt: &Interrupt,
+) -> Result<Outcome, Box<dyn std::error::Error>> {
+    // Help is answered locally, without a round trip to the server
+    let mut words = input.split_whitespace();
+    let word = words.next();
+    if word == Some("help") {
+        let topic = words.next();
+        if let Some(text) = commands::help_text(topic) {
+            if args.json {
+                events::emit(&Event::Help { text });
+            } else {
+                println!("{}", text);
+            }
+            return Ok(Outcome::Success);
+        }
+
+        return Ok(fail(
+            args,
+            ExitCode::Generic,
+            format!(
+                "Unknown command '{}'. Use 'help' to see more",
+                topic.unwrap_or("")
+            ),
+        ));
+    }
+
+    if word == Some("whoami") {
+        if args.json {
+            events::emit(&Event::Whoami {
+                username: session.username.clone(),
+            });
+        } else {
+            println!("@{}", session.username);
+        }
+        return Ok(Outcome::Success);
+    }
+
+    if word == Some("version") {
+        if args.json {
+            events::emit(&Event::Version {
+                version: VERSION.to_string(),
+            });
+        } else {
+            println!("glide-cli {}", VERSION);
+        }
+        return Ok(Outcome::Success);
+    }
+
+    if let Some(command @ ("block" | "unblock" | "blocks")) = word {
+        return Ok(update_blocklist(
+            command,
+            words.next(),
+            &mut session.blocklist,
+            args,
+        ));
+    }
+
+    if word == Some("ping") {
+        return ping(client, words.next(), args).await;
+    }
+
+    let (input, options) = match line_options(input) {
+        Ok(parsed) => parsed,
+        Err(message) => return Ok(fail(args, ExitCode::Generic, message)),
+    };
+    let mut input = input.as_str();
+
+    let resolved;
+    if let Some((reply, number)) = request_number(input) {
+        match resolve_request_number(client, number).await? {
+            Ok(sender) => {
+                resolved = format!("{} @{}", reply, sender);
+                input = &resolved;
+            }
+            Err(message) => return Ok(fail(args, ExitCode::Transfer, message)),
+        }
+    }
+
+    // `glide - @<user> --name <name>` sends whatever arrives on stdin
+    match spool_stdin_upload(input, args) {
+        Ok(Some(upload)) => {
+            // The spooled file goes away with `upload` once this returns
+            return send_command(client, &upload.input, session, args, interrupt, &options).await;
+        }
+        Ok(None) => {}
+        Err(message) => return Ok(fail(args, ExitCode::Transfer, message)),
+    }
+
+    // `glide a.txt b.txt @<user>` sends each file as its own glide request
+    if let Some(batch) = split_glide_batch(input) {
+        return send_batch(client, &batch, session, args, interrupt, &options).await;
+    }
+
+    send_command(client, input, session, args, interrupt, &options).await
+}
+
+// Fill in the --prompt template
+fn prompt(session: &Session, args: &Args) -> String {
+    let cwd = std::env::current_dir()
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_default();
+
+    args.prompt
+        .replace("{user}", &session.username)
+        .replace("{host}", &args.host)
+        .replace("{cwd}", &cwd)
+}
+
+// Print a request that arrived while the prompt was waiting above the prompt.
+// Requests from blocked users aren't announced.
+fn announce_request(request: &IncomingRequest, session: &Session, args: &Args, interactive: bool) {
+    if session.blocklist.contains(&request.from) {
+        return;
+    }
+
+    if args.json {
+        events::emit(&Event::IncomingRequest {
+            from: request.from.clone(),
+            file: request.file.clone(),
+        });
+        return;
+    }
+
+    // Anything typed so far stays in the line being read, only its echo is cleared
+    print!("\r");
+    let _ = crossterm::execute!(io::stdout(), Clear(ClearType::CurrentLine));
+    println!(
+        "* New file request from @{}: {}",
+        request.from, request.file
+    );
+    if interactive {
+        print!("{}", prompt(session, args));
+    }
+    let _ = io::stdout().flush();
+}
+
+// Time round trips to the server. There's no dedicated ping frame, so a `list`
+// request stands in as the lightest request the server answers.
+async fn ping(
+    client: &mut Client,
+    count: Option<&str>,
+    args: &Args,
+) -> Result<Outcome, Box<dyn std::error::Error>> {
+    let count = match count.map(str::parse::<u32>) {
+        None => 1,
+        Some(Ok(count)) if count > 0 => count,
+        _ => {
+            return Ok(fail(
+                args,
+                ExitCode::Generic,
+                "'ping' expects a positive count, e.g. 'ping 5'".to_string(),
+            ))
+        }
+    };
+
+    let mut samples = Vec::new();
+    for n in 1..=count {
+        let start = Instant::now();
+        client.list_users().await?;
+        let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+
+        if !args.json {
+            println!("Reply {}: {:.1} ms", n, elapsed);
+        }
+        samples.push(elapsed);
+    }
+
+    if args.json {
+        events::emit(&Event::Ping {
+            replies_ms: samples,
+        });
+    } else if count > 1 {
+        let min = samples.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = samples.iter().copied().fold(0.0, f64::max);
+        let avg = samples.iter().sum::<f64>() / samples.len() as f64;
+        println!(
+            "{} replies, min/avg/max = {:.1}/{:.1}/{:.1} ms",
+            count, min, avg, max
+        );
+    }
+
+    Ok(Outcome::Success)
+}
+
+// Handle `block @<user>`, `unblock @<user>` and `blocks` against the local blocklist
+fn update_blocklist(
+    command: &str,
+    user: Option<&str>,
+    blocklist: &mut Blocklist,
+    args: &Args,
+) -> Outcome {
+    let message = if command == "blocks" {
+        let users: Vec<&str> = blocklist.users().map(String::as_str).collect();
+        if users.is_empty() {
+            "No users are blocked".to_string()
+        } else {
+            format!("Blocked users:\n @{}", users.join("\n @"))
+        }
+    } else {
+        let Some(user) = user else {
+            return fail(
+                args,
+                ExitCode::Generic,
+                format!("Usage: {} @<user>", command),
+            );
+        };
+        let user = match target_user(user) {
+            Ok(user) => user,
+            Err(message) => return fail(args, ExitCode::Generic, message),
+        };
+
+        let changed = match command {
+            "block" => blocklist.block(user),
+            _ => blocklist.unblock(user),
+        };
+        match (command, changed) {
+            (_, Err(e)) => {
+                return fail(
+                    args,
+                    ExitCode::Generic,
+                    format!("Unable to save the blocklist: {}", e),
+                )
+            }
+            ("block", Ok(true)) => format!("Blocked @{}", user),
+            ("block", Ok(false)) => format!("@{} is already blocked", user),
+            (_, Ok(true)) => format!("Unblocked @{}", user),
+            (_, Ok(false)) => format!("@{} isn't blocked", user),
+        }
+    };
+
+    if args.json {
+        events::emit(&Event::Blocked {
+            users: blocklist.users().cloned().collect(),
+        });
+    } else {
+        println!("{}", message);
+    }
+
+    Outcome::Success
+}
+
+// Options on a command line that the client acts on itself, never sent to the server
+#[derive(Default)]
+struct LineOptions {
+    // `glide --dry-run ...` checks everything locally and stops before the request
+    dry_run: bool,
+    // `ok @<user> as <name>` saves the file under a name of the receiver's choosing
+    save_as: Option<String>,
+}
+
+// Take the client's own options out of a command line, returning the rest of it
+fn line_options(input: &str) -> Result<(String, LineOptions), String> {
+    // A line that doesn't tokenize is reported when it's parsed as a single command
+    let Ok(words) = tokenize(input) else {
+        return Ok((input.to_string(), LineOptions::default()));
+    };
+
+    match words.first().map(String::as_str) {
+        Some("glide") if words.iter().any(|word| word == "--dry-run") => {
+            let words: Vec<String> = words
+                .iter()
+                .filter(|word| *word != "--dry-run")
+                .map(|word| quote(word))
+                .collect();
+            let options = LineOptions {
+                dry_run: true,
+                ..LineOptions::default()
+            };
+            Ok((words.join(" "), options))
+        }
+        Some("ok") if words.len() == 4 && words[2] == "as" => {
+            let name = &words[3];
+            if Path::new(name).file_name() != Some(OsStr::new(name)) {
+                return Err(format!("'{}' isn't a valid file name", name));
             }
-            Command::Requests => {
-                let Transmission::IncomingRequests(reqs) = response else {
-                    println!("Command failed\n{:#?}", response);
-                    return Ok(());
-                };
 
-                println!("Incoming requests:");
-                for req in reqs.iter() {
-                    println!(" From: {}, File: {}", req.sender, req.filename);
+            let options = LineOptions {
+                save_as: Some(name.clone()),
+                ..LineOptions::default()
+            };
+            Ok((format!("ok {}", quote(&words[1])), options))
+        }
+        _ => Ok((input.to_string(), LineOptions::default())),
+    }
+}
+
+// `ok #<n>` and `no #<n>` pick a request by its place in the `reqs` listing
+fn request_number(input: &str) -> Option<(&str, usize)> {
+    let mut words = input.split_whitespace();
+    let (Some(reply @ ("ok" | "no")), Some(number), None) =
+        (words.next(), words.next(), words.next())
+    else {
+        return None;
+    };
+
+    Some((reply, number.strip_prefix('#')?.parse().ok()?))
+}
+
+// The server only knows requests by their sender, so a request number is turned into
+// the sender's name. That can't single out one of several requests from one sender.
+async fn resolve_request_number(
+    client: &mut Client,
+    number: usize,
+) -> Result<Result<String, String>, Box<dyn std::error::Error>> {
+    let requests = client.incoming_requests().await?;
+    let Some(request) = number.checked_sub(1).and_then(|n| requests.get(n)) else {
+        return Ok(Err(match requests.len() {
+            0 => "There are no pending requests".to_string(),
+            pending => format!("There's no request #{}, {} are pending", number, pending),
+        }));
+    };
+
+    let from_sender = requests
+        .iter()
+        .filter(|other| other.from == request.from)
+        .count();
+    if from_sender > 1 {
+        return Ok(Err(format!(
+            "Request #{} can't be picked out, @{} has {} pending requests and the server only \
+             tells them apart by sender",
+            number, request.from, from_sender
+        )));
+    }
+
+    Ok(Ok(request.from.clone()))
+}
+
+// Split a glide with several paths into one glide command per path
+fn split_glide_batch(input: &str) -> Option<Vec<String>> {
+    // A line that doesn't tokenize is reported when it's parsed as a single command
+    let words = tokenize(input).ok()?;
+    let (Some("glide"), Some(to)) = (words.first().map(String::as_str), words.last()) else {
+        return None;
+    };
+    if words.len() <= 3 {
+        return None;
+    }
+
+    Some(
+        words[1..words.len() - 1]
+            .iter()
+            .map(|path| format!("glide {} {}", quote(path), quote(to)))
+            .collect(),
+    )
+}
+
+// Send each file in turn. A failed file stops the rest unless --keep-going was given.
+async fn send_batch(
+    client: &mut Client,
+    batch: &[String],
+    session: &Session,
+    args: &Args,
+    interrupt: &Interrupt,
+    options: &LineOptions,
+) -> Result<Outcome, Box<dyn std::error::Error>> {
+    let mut failure = None;
+
+    for (n, input) in batch.iter().enumerate() {
+        if !args.quiet {
+            println!("File {} of {}", n + 1, batch.len());
+        }
+
+        match send_command(client, input, session, args, interrupt, options).await? {
+            Outcome::Success => {}
+            Outcome::Failure(code) => {
+                failure = Some(code);
+                if !args.keep_going {
+                    let skipped = batch.len() - n - 1;
+                    if skipped > 0 && !args.quiet {
+                        println!("Skipping the remaining {} file(s)", skipped);
+                    }
+                    break;
                 }
             }
-            _ => {}
+            ended @ (Outcome::SessionEnded(_) | Outcome::Cancelled) => return Ok(ended),
         }
     }
 
-    Ok(())
+    Ok(match failure {
+        Some(code) => Outcome::Failure(code),
+        None => Outcome::Success,
+    })
 }
 
-async fn login(stream: &mut TcpStream) -> Result<String, Box<dyn std::error::Error>> {
-    let mut username = String::new();
+// Parse, validate and send a command for the server, then handle its response.
+// A dry run stops a glide once it has passed validation.
+async fn send_command(
+    client: &mut Client,
+    input: &str,
+    session: &Session,
+    args: &Args,
+    interrupt: &Interrupt,
+    options: &LineOptions,
+) -> Result<Outcome, Box<dyn std::error::Error>> {
+    let (command, upload_size) = match prepare_command(input) {
+        Ok(prepared) => prepared,
+        Err((code, message)) => return Ok(fail(args, code, message)),
+    };
 
-    loop {
-        username.clear();
-        print!("Enter your username: ");
-        io::stdout().flush()?;
-        io::stdin().read_line(&mut username)?;
+    if let (true, Command::Glide { path, to }) = (options.dry_run, &command) {
+        report_dry_run(path, &to.to_string(), upload_size, args);
+        return Ok(Outcome::Success);
+    }
 
-        let username = username.trim();
+    // Send command to the server
+    let response = client.request(command.clone()).await?;
+
+    // A response that doesn't belong to the command means the two sides disagree on
+    // where the protocol is, so the command stops before anything more is sent for it
+    if !is_expected(&command, &response) {
+        log::warn!("Unexpected response to '{}': {:?}", command, response);
+        let code = match command {
+            Command::Glide { .. } | Command::Ok(_) => ExitCode::Transfer,
+            _ => ExitCode::Generic,
+        };
+        return Ok(fail(
+            args,
+            code,
+            format!("'{}' failed, the server sent\n{:#?}", command, response),
+        ));
+    }
+
+    match command {
+        Command::Glide { path, to } => {
+            if matches!(response, Transmission::UsernameInvalid) {
+                // The server has no separate answer for unknown and offline users
+                return Ok(fail(
+                    args,
+                    ExitCode::Transfer,
+                    format!(
+                        "Unable to send glide request, @{} isn't connected",
+                        to.to_string().trim_start_matches('@')
+                    ),
+                ));
+            }
+
+            return upload(client, path, to.to_string(), upload_size, args, interrupt).await;
+        }
+        Command::Ok(from) => {
+            if matches!(response, Transmission::OkSuccess) {
+                return download(
+                    client,
+                    from.to_string(),
+                    options.save_as.as_deref(),
+                    args,
+                    interrupt,
+                )
+                .await;
+            } else {
+                return Ok(fail(
+                    args,
+                    ExitCode::Transfer,
+                    "`ok` command failed! Invalid request".to_string(),
+                ));
+            }
+        }
+        Command::List => {
+            let Transmission::ConnectedUsers(users) = response else {
+                unreachable!("checked by is_expected");
+            };
 
-        if !validate_username(username) {
-            println!(
-                "Invalid username!
-Usernames must follow these rules:
-    • Only alphanumeric characters and periods (.) are allowed.
-    • Must be 1 to 10 characters long.
-    • Cannot start or end with a period (.).
-    • Cannot contain consecutive periods (..).
+            if args.json {
+                events::emit(&Event::Users {
+                    users: users.iter().map(|user| user.to_string()).collect(),
+                });
+                return Ok(Outcome::Success);
+            }
 
-Please try again with a valid username."
+            // A script's output is for whatever runs it, so it's never paged or styled
+            let scripted = args.script.is_some();
+            let users: Vec<String> = users.iter().map(|user| user.to_string()).collect();
+            pager::show(
+                &user_list::lines(&users, &session.username, scripted),
+                !args.no_pager && !scripted,
             );
+        }
+        Command::Requests => {
+            let Transmission::IncomingRequests(reqs) = response else {
+                unreachable!("checked by is_expected");
+            };
+
+            if args.json {
+                let requests = reqs
+                    .iter()
+                    .enumerate()
+                    .map(|(n, req)| RequestEntry {
+                        number: n + 1,
+                        from: req.sender.to_string(),
+                        file: req.filename.to_string(),
+                        blocked: session.blocklist.contains(&req.sender.to_string()),
+                    })
+                    .collect();
+                events::emit(&Event::Requests { requests });
+                return Ok(Outcome::Success);
+            }
+
+            // Numbered for `ok #<n>` and `no #<n>`, padded so the columns line up
+            let width = format!("[#{}]", reqs.len()).len();
+            let mut lines = vec!["Incoming requests:".to_string()];
+            lines.extend(reqs.iter().enumerate().map(|(n, req)| {
+                let blocked = session.blocklist.contains(&req.sender.to_string());
+                format!(
+                    " {:<width$} From: @{}, File: {}{}",
+                    format!("[#{}]", n + 1),
+                    req.sender,
+                    req.filename,
+                    if blocked { " (blocked)" } else { "" }
+                )
+            }));
+            pager::show(&lines, !args.no_pager && args.script.is_none());
+        }
+        Command::No(from) => {
+            let from = from.to_string().trim_start_matches('@').to_string();
+            if args.json {
+                events::emit(&Event::Declined { from });
+            } else {
+                println!("Declined the request from @{}", from);
+            }
+        }
+        _ => {}
+    }
+
+    Ok(Outcome::Success)
+}
+
+// An upload read from stdin, spooled into a temporary file so it can be sent like
+// any other file once its size is known
+struct StdinUpload {
+    // The glide command rewritten to point at the spooled file
+    input: String,
+    // Removed when the upload is dropped
+    _dir: tempfile::TempDir,
+}
+
+fn spool_stdin_upload(input: &str, args: &Args) -> Result<Option<StdinUpload>, String> {
+    let words = tokenize(input)?;
+    if words.len() < 2 || words[0] != "glide" || words[1] != "-" {
+        return Ok(None);
+    }
+
+    // The script itself is what arrives on stdin, so there's no file to read there
+    if args.script.as_deref() == Some("-") {
+        return Err("A script read from stdin can't also send stdin with 'glide -'".to_string());
+    }
+
+    let mut name = None;
+    let mut rest = Vec::new();
+    let mut iter = words[2..].iter();
+    while let Some(word) = iter.next() {
+        if word == "--name" {
+            name = iter.next().map(String::as_str);
+        } else {
+            rest.push(quote(word));
+        }
+    }
+
+    let Some(name) = name else {
+        return Err(
+            "Sending from stdin needs a file name, e.g. 'glide - @bob --name report.txt'"
+                .to_string(),
+        );
+    };
+
+    // The name ends up on the receiver's disk, so it has to be a bare file name
+    if Path::new(name).file_name() != Some(OsStr::new(name)) {
+        return Err(format!("'{}' isn't a valid file name", name));
+    }
+
+    // On stderr, so it stays out of output that's being parsed
+    if io::stdin().is_terminal() && !args.quiet {
+        eprintln!("Reading '{}' from stdin, press Ctrl+D to finish", name);
+    }
+
+    // A fresh directory only this user can reach, so nobody else can plant the file
+    // it's spooled into beforehand
+    let spool = || -> io::Result<(tempfile::TempDir, PathBuf)> {
+        let dir = tempfile::Builder::new().prefix("glide-stdin-").tempdir()?;
+        let path = dir.path().join(name);
+        let mut file = std::fs::File::create_new(&path)?;
+        io::copy(&mut io::stdin().lock(), &mut file)?;
+        Ok((dir, path))
+    };
+
+    let (dir, path) = spool().map_err(|e| format!("Unable to read stdin: {}", e))?;
+
+    Ok(Some(StdinUpload {
+        input: format!(
+            "glide {} {}",
+            quote(&path.to_string_lossy()),
+            rest.join(" ")
+        ),
+        _dir: dir,
+    }))
+}
+
+// The responses each command can get from the server. Any reply to `ok` that isn't
+// OkSuccess is the server refusing it, which the `ok` handling reports itself.
+fn is_expected(command: &Command, response: &Transmission) -> bool {
+    match command {
+        Command::Glide { .. } => matches!(
+            response,
+            Transmission::GlideRequestSent | Transmission::UsernameInvalid
+        ),
+        Command::List => matches!(response, Transmission::ConnectedUsers(_)),
+        Command::Requests => matches!(response, Transmission::IncomingRequests(_)),
+        Command::No(_) => matches!(response, Transmission::NoSuccess),
+        _ => true,
+    }
+}
+
+// Parse and validate a command for the server, returning it with the size of a
+// glide's file, or the exit code and message it fails with
+fn prepare_command(input: &str) -> Result<(Command, u64), (ExitCode, String)> {
+    let command = parse_command(input).map_err(|message| (ExitCode::Generic, message))?;
+
+    if !validate_command(&command.to_string()) {
+        return Err((
+            ExitCode::Generic,
+            format!("Invalid command '{}'. Use 'help' to see more", input),
+        ));
+    }
+
+    // Validate glide command, opening the file once so the size comes from the
+    // same handle that proved it readable. The recipient was already checked while
+    // parsing, before any round trip.
+    let upload_size = match &command {
+        Command::Glide { path, to: _ } => {
+            inspect_upload(path).map_err(|message| (ExitCode::Transfer, message))?
+        }
+        _ => 0,
+    };
+
+    Ok((command, upload_size))
+}
+
+fn report_dry_run(path: &str, to: &str, size: u64, args: &Args) {
+    let to = to.trim_start_matches('@').to_string();
+    if args.json {
+        events::emit(&Event::DryRun {
+            file: path.to_string(),
+            to,
+            size,
+        });
+    } else {
+        println!("Would send '{}' ({}) to @{}", path, format_bytes(size), to);
+    }
+}
+
+// `--dry-run` goes through a script without connecting, checking each glide line
+// as `glide --dry-run` would. Lines that need the server aren't checked, nor is
+// `glide -`, whose file only exists once stdin has been read.
+fn dry_run_script(args: &Args) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let script: Box<dyn BufRead> = match args.script.as_deref() {
+        Some(path) if path != "-" => Box::new(io::BufReader::new(std::fs::File::open(path)?)),
+        // --dry-run is only accepted alongside --script
+        _ => Box::new(io::BufReader::new(io::stdin())),
+    };
+    let mut first_failure = None;
+
+    for line in script.lines() {
+        let line = line?;
+        let input = commands::normalize(line.trim());
+        if input == "exit" {
+            break;
+        }
+        if !input.starts_with("glide ") || input.starts_with("glide - ") {
             continue;
         }
 
-        // Send the username to the server
-        stream
-            .write_all(
-                Transmission::Username(username.to_string())
-                    .to_bytes()
-                    .as_slice(),
-            )
-            .await?;
+        let input = match line_options(&input) {
+            Ok((input, _)) => input,
+            Err(message) => {
+                fail(args, ExitCode::Generic, message);
+                first_failure.get_or_insert(ExitCode::Generic);
+                continue;
+            }
+        };
+        let batch = split_glide_batch(&input).unwrap_or_else(|| vec![input]);
+        for glide in &batch {
+            match prepare_command(glide) {
+                Ok((Command::Glide { path, to }, size)) => {
+                    report_dry_run(&path, &to.to_string(), size, args)
+                }
+                Ok(_) => {}
+                Err((code, message)) => {
+                    fail(args, code, message);
+                    first_failure.get_or_insert(code);
+                }
+            }
+        }
 
-        // Wait for the server's response
-        let response = Transmission::from_stream(stream).await?;
-        if matches!(response, Transmission::UsernameOk) {
-            println!("You are now connected as @{}", username);
+        if first_failure.is_some() && !args.keep_going {
             break;
+        }
+    }
+
+    Ok(first_failure.unwrap_or(ExitCode::Success))
+}
+
+// `Command::parse` splits on whitespace, so the line is tokenized first and a quoted
+// glide path is put back into the parsed command
+fn parse_command(input: &str) -> Result<Command, String> {
+    let words = tokenize(input)?;
+
+    match words.as_slice() {
+        [glide, path, to] if glide == "glide" => {
+            let mut command = Command::parse(&format!("glide _ @{}", target_user(to)?));
+            if let Command::Glide { path: parsed, .. } = &mut command {
+                *parsed = path.clone();
+            }
+            Ok(command)
+        }
+        [reply, from] if reply == "ok" || reply == "no" => Ok(Command::parse(&format!(
+            "{} @{}",
+            reply,
+            target_user(from)?
+        ))),
+        _ => Ok(Command::parse(&words.join(" "))),
+    }
+}
+
+// Usernames may be typed with or without the leading @. Returns the bare name.
+fn target_user(word: &str) -> Result<&str, String> {
+    let user = word.strip_prefix('@').unwrap_or(word);
+    if user.starts_with('@') {
+        return Err(format!("'{}' has more than one @", word));
+    }
+
+    validate_username(user).map_err(|e| format!("'{}' isn't a valid username: {}", word, e))?;
+    Ok(user)
+}
+
+// Check that a file can be uploaded and return its size
+fn inspect_upload(path: &str) -> Result<u64, String> {
+    let file = std::fs::File::open(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => format!("Path '{}' is invalid. File does not exist", path),
+        io::ErrorKind::PermissionDenied => {
+            format!("Unable to read '{}': permission denied", path)
+        }
+        _ => format!("Unable to open '{}': {}", path, e),
+    })?;
+
+    let metadata = file
+        .metadata()
+        .map_err(|e| format!("Unable to read '{}': {}", path, e))?;
+    if !metadata.is_file() {
+        return Err(format!("Path '{}' is invalid. Not a file", path));
+    }
+
+    Ok(metadata.len())
+}
+
+// Stream a file to the server once it has accepted the glide request
+async fn upload(
+    client: &mut Client,
+    path: String,
+    to: String,
+    size: u64,
+    args: &Args,
+    interrupt: &Interrupt,
+) -> Result<Outcome, Box<dyn std::error::Error>> {
+    if !args.quiet {
+        println!("Sending '{}' ({})", path, format_bytes(size));
+    }
+
+    let start = Instant::now();
+    let result = interrupt.guard(client.send_file(&path)).await;
+    let elapsed = start.elapsed();
+
+    log_transfer(
+        args,
+        transfer_log::Entry {
+            direction: Direction::Upload,
+            peer: &to,
+            file: &path,
+            size: Some(size),
+            status: status_of(&result),
+        },
+    );
+
+    match result {
+        Err(e) if e.is::<Cancelled>() => Ok(end_cancelled_session(args)),
+        Err(e) => Err(TransferError(e).into()),
+        Ok(_) => {
+            if args.json {
+                events::emit(&Event::TransferComplete {
+                    direction: Direction::Upload,
+                    file: path,
+                    size,
+                    seconds: elapsed.as_secs_f64(),
+                });
+            } else {
+                println!("Sent {}", format_speed(size, elapsed));
+            }
+            Ok(Outcome::Success)
+        }
+    }
+}
+
+// Receive the file from an accepted request
+async fn download(
+    client: &mut Client,
+    from: String,
+    save_as: Option<&str>,
+    args: &Args,
+    interrupt: &Interrupt,
+) -> Result<Outcome, Box<dyn std::error::Error>> {
+    // A script's output stays plain, so only someone at the prompt gets the bar
+    let progress = Progress::new(args.quiet || args.script.is_some());
+    let on_collision = |name: &str| -> io::Result<Collision> {
+        progress.clear();
+        if args.overwrite {
+            return Ok(Collision::Overwrite);
+        }
+
+        // Nobody is there to answer in a script or under --json, and a script on stdin
+        // would have its next lines taken as answers, so the file is left alone
+        let ask = args.script.is_none() && !args.json && io::stdin().is_terminal();
+        let choice = if ask {
+            prompt_collision(name)?
         } else {
-            println!(
-                "Server rejected username: {}",
-                match response {
-                    Transmission::UsernameTaken => "Username is taken",
-                    Transmission::UsernameInvalid => "Username is invalid",
-                    _ => unreachable!(),
+            Collision::Skip
+        };
+        if matches!(choice, Collision::Skip) {
+            if args.json {
+                events::emit(&Event::Skipped {
+                    file: name.to_string(),
+                });
+            } else if ask {
+                println!("Skipped '{}'", name);
+            } else {
+                println!(
+                    "Skipped '{}', it already exists. Use --overwrite to replace it",
+                    name
+                );
+            }
+        }
+        Ok(choice)
+    };
+    let receive = client.receive_file(
+        &args.output_dir,
+        save_as,
+        args.transfer_timeout,
+        on_collision,
+        |received, total| progress.update(received, total),
+    );
+    let start = Instant::now();
+    let result = interrupt.guard(receive).await;
+    let elapsed = start.elapsed();
+    progress.clear();
+
+    // The absolute path, so the file can be found whatever the output directory was
+    let saved = match &result {
+        Ok(Some(saved)) => std::fs::canonicalize(saved)
+            .unwrap_or_else(|_| saved.clone())
+            .display()
+            .to_string(),
+        _ => String::new(),
+    };
+    let size = std::fs::metadata(&saved)
+        .map(|metadata| metadata.len())
+        .ok();
+    log_transfer(
+        args,
+        transfer_log::Entry {
+            direction: Direction::Download,
+            peer: &from,
+            // A file that never made it into place is logged under the sender's name
+            file: match &result {
+                Ok(Some(_)) => &saved,
+                _ => client.last_received().unwrap_or_default(),
+            },
+            size,
+            status: match &result {
+                Ok(None) => Status::Skipped,
+                result => status_of(result),
+            },
+        },
+    );
+
+    match result {
+        Err(e) if e.is::<Cancelled>() => Ok(end_cancelled_session(args)),
+        Err(e) => Err(TransferError(e).into()),
+        Ok(None) => Ok(Outcome::Success),
+        Ok(Some(_)) => {
+            if args.json {
+                events::emit(&Event::TransferComplete {
+                    direction: Direction::Download,
+                    file: saved.clone(),
+                    size: size.unwrap_or(0),
+                    seconds: elapsed.as_secs_f64(),
+                });
+            } else {
+                if !args.quiet {
+                    println!("Received {}", format_speed(size.unwrap_or(0), elapsed));
                 }
+                println!("Saved to {}", saved);
+            }
+
+            if args.open {
+                if let Err(e) = open::open(Path::new(&saved)) {
+                    log::warn!("Unable to open {}: {}", saved, e);
+                }
+            }
+            Ok(Outcome::Success)
+        }
+    }
+}
+
+fn status_of<T>(result: &Result<T, Box<dyn std::error::Error>>) -> Status {
+    match result {
+        Ok(_) => Status::Completed,
+        Err(e) if e.is::<Cancelled>() => Status::Cancelled,
+        Err(_) => Status::Failed,
+    }
+}
+
+// A broken log shouldn't get in the way of transfers, so failures are only reported
+fn log_transfer(args: &Args, entry: transfer_log::Entry) {
+    let Some(path) = &args.log_file else {
+        return;
+    };
+
+    if let Err(e) = transfer_log::append(path, &entry) {
+        eprintln!("Unable to write to transfer log {}: {}", path.display(), e);
+    }
+}
+
+// Report why a command failed
+fn fail(args: &Args, code: ExitCode, message: String) -> Outcome {
+    if args.json {
+        events::emit(&Event::Error { message });
+    } else {
+        eprintln!("{}", message);
+    }
+
+    Outcome::Failure(code)
+}
+
+// The protocol has no way to abort a transfer midway, so the stream can't be
+// trusted after a cancel and the session has to end. A script ends with it, while
+// at the prompt the client logs back in.
+fn end_cancelled_session(args: &Args) -> Outcome {
+    let (message, outcome) = if args.script.is_some() {
+        (
+            "Transfer cancelled. The connection was closed as the transfer couldn't be stopped midway.",
+            Outcome::SessionEnded(ExitCode::Transfer),
+        )
+    } else {
+        (
+            "Transfer cancelled. Reconnecting, as the transfer couldn't be stopped midway.",
+            Outcome::Cancelled,
+        )
+    };
+    if args.json {
+        events::emit(&Event::Error {
+            message: message.to_string(),
+        });
+    } else {
+        println!("{}", message);
+    }
+    outcome
+}
+
+// IPv6 literals need brackets before a port can be appended
+fn format_address(host: &str, port: &str) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+// Reconnect and log back in under the same name. Returns None if no username
+// was accepted.
+async fn resume_session(
+    address: &str,
+    username: String,
+    stdin: &mut StdinLines,
+    args: &Args,
+) -> Result<Option<(Client, String)>, Box<dyn std::error::Error>> {
+    let mut client = reconnect(address, args).await;
+    let username = login(&mut client, Some(username), stdin, args).await?;
+
+    Ok(username.map(|username| (client, username)))
+}
+
+// Re-dial the server until it answers, doubling the wait between attempts
+async fn reconnect(address: &str, args: &Args) -> Client {
+    let mut delay = Duration::from_secs(1);
+    let mut attempt = 1;
+
+    loop {
+        log::info!("Reconnecting to {} (attempt {})...", address, attempt);
+
+        match Client::connect(address, args.connect_timeout, args.io_timeout).await {
+            Ok(client) => return client,
+            Err(e) => log::info!("Reconnect failed: {}. Retrying in {}s", e, delay.as_secs()),
+        }
+
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        attempt += 1;
+    }
+}
+
+// Ask what to do about a taken name, answered with a single key press
+fn prompt_collision(file_name: &str) -> io::Result<Collision> {
+    print!(
+        "File '{}' exists. [o]verwrite / [r]ename / [s]kip? ",
+        file_name
+    );
+    io::stdout().flush()?;
+
+    loop {
+        let choice = match pager::read_key()? {
+            KeyCode::Char('o' | 'O') => Collision::Overwrite,
+            KeyCode::Char('r' | 'R') => Collision::Rename,
+            KeyCode::Char('s' | 'S' | 'q') | KeyCode::Esc => Collision::Skip,
+            _ => continue,
+        };
+        println!();
+        return Ok(choice);
+    }
+}
+
+// Show a prompt for input. Under --json it goes to stderr, keeping stdout to one
+// event per line.
+fn ask(prompt: &str, args: &Args) -> io::Result<()> {
+    if args.json {
+        eprint!("{}", prompt);
+        io::stderr().flush()
+    } else {
+        print!("{}", prompt);
+        io::stdout().flush()
+    }
+}
+
+// Returns None if input runs out before the server accepts a username
+async fn login(
+    client: &mut Client,
+    preset: Option<String>,
+    stdin: &mut StdinLines,
+    args: &Args,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    // Try the username given on the command line first, falling back to the prompt
+    if let Some(username) = preset {
+        if let Err(e) = validate_username(&username) {
+            fail(
+                args,
+                ExitCode::Auth,
+                format!("Username '{}' is invalid: {}", username, e),
             );
+        } else if try_username(client, &username, args).await? {
+            return Ok(Some(username));
         }
     }
 
-    Ok(username)
+    loop {
+        ask("Enter your username: ", args)?;
+        let username = stdin.read_line().await?;
+        if username.is_empty() {
+            eprintln!("\nNo username was accepted, giving up");
+            return Ok(None);
+        }
+
+        let username = username.trim();
+
+        if let Err(e) = validate_username(username) {
+            fail(
+                args,
+                ExitCode::Auth,
+                format!("Invalid username! {}. Please try again.", e),
+            );
+            continue;
+        }
+
+        if try_username(client, username, args).await? {
+            return Ok(Some(username.to_string()));
+        }
+    }
 }
 
-fn validate_username(username: &str) -> bool {
-    let re = Regex::new(r"^[a-zA-Z0-9](?:[a-zA-Z0-9\.]{0,8}[a-zA-Z0-9])?$").unwrap();
-    re.is_match(username)
+// Send a username to the server, returning whether it was accepted
+async fn try_username(
+    client: &mut Client,
+    username: &str,
+    args: &Args,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    // Wait for the server's response
+    let spinner = Spinner::start("Logging in...", args.quiet);
+    let response = client.login(username).await;
+    spinner.stop().await;
+
+    let response = response?;
+    if response == Login::Accepted {
+        if args.json {
+            events::emit(&Event::LoggedIn {
+                username: username.to_string(),
+            });
+        } else if !args.quiet {
+            println!("You are now connected as @{}", username);
+        }
+        return Ok(true);
+    }
+
+    fail(
+        args,
+        ExitCode::Auth,
+        format!(
+            "Server rejected username: {}",
+            match response {
+                Login::Taken => "Username is taken",
+                _ => "Username is invalid",
+            }
+        ),
+    );
+
+    Ok(false)
 }
 
 pub fn validate_command(input: &str) -> bool {
@@ -194,3 +1471,60 @@ pub fn validate_command(input: &str) -> bool {
         || ok_re.is_match(input)
         || no_re.is_match(input)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_user_takes_one_optional_at() {
+        assert_eq!(target_user("@bob"), Ok("bob"));
+        assert_eq!(target_user("bob"), Ok("bob"));
+        assert!(target_user("@@bob").is_err());
+    }
+
+    #[test]
+    fn target_user_must_be_a_valid_username() {
+        assert!(target_user("@").is_err());
+        assert!(target_user("@bob!").is_err());
+    }
+
+    #[test]
+    fn ok_as_takes_off_the_local_name() {
+        let (line, options) = line_options("ok @bob as x.txt").unwrap();
+        assert_eq!(line, "ok @bob");
+        assert_eq!(options.save_as.as_deref(), Some("x.txt"));
+        assert!(!options.dry_run);
+    }
+
+    #[test]
+    fn ok_as_works_with_a_request_number() {
+        let (line, options) = line_options("ok #2 as x.txt").unwrap();
+        assert_eq!(line, "ok #2");
+        assert_eq!(options.save_as.as_deref(), Some("x.txt"));
+    }
+
+    #[test]
+    fn ok_as_rejects_a_path() {
+        assert!(line_options("ok @bob as ../x").is_err());
+        assert!(line_options("ok @bob as dir/x.txt").is_err());
+    }
+
+    #[test]
+    fn other_lines_pass_through() {
+        let (line, options) = line_options("ok @bob").unwrap();
+        assert_eq!(line, "ok @bob");
+        assert!(options.save_as.is_none());
+    }
+
+    #[test]
+    fn format_address_brackets_bare_ipv6() {
+        assert_eq!(format_address("::1", "8080"), "[::1]:8080");
+        assert_eq!(format_address("[::1]", "8080"), "[::1]:8080");
+        assert_eq!(format_address("127.0.0.1", "8080"), "127.0.0.1:8080");
+        assert_eq!(
+            format_address("glide.example.com", "8080"),
+            "glide.example.com:8080"
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// Open a file with the platform's default application, without waiting for it
+pub fn open(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        // `start` is built into cmd, and its first quoted argument is a window title
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+
+    command
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
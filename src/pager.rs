@@ -0,0 +1,132 @@
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use regex::Regex;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+const PROMPT: &str = "-- More -- (space: next page, enter: next line, q: quit)";
+
+// Print `lines`, paging them if they don't fit on the terminal. $PAGER is used when
+// set, otherwise a minimal built-in pager.
+pub fn show(lines: &[String], enabled: bool) {
+    let height = terminal::size().map(|(_, rows)| rows as usize).unwrap_or(0);
+
+    if !enabled || !io::stdout().is_terminal() || height < 2 || lines.len() < height {
+        for line in lines {
+            println!("{}", line);
+        }
+        return;
+    }
+
+    if let Ok(pager) = env::var("PAGER") {
+        match external(&pager, lines) {
+            Ok(()) => return,
+            // Fall back to the built-in pager rather than losing the output
+            Err(e) => log::warn!("Unable to run PAGER '{}': {}", pager, e),
+        }
+    }
+
+    if let Err(e) = internal(lines, height) {
+        log::warn!("Paging failed: {}", e);
+    }
+}
+
+// Raw mode is only on while the pager waits for a key, but a panic or error at that
+// point would otherwise leave the shell without echo after the client exits
+pub fn restore_terminal_on_panic() {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = terminal::disable_raw_mode();
+        default(info);
+    }));
+}
+
+pub fn restore_terminal() {
+    let _ = terminal::disable_raw_mode();
+}
+
+fn external(pager: &str, lines: &[String]) -> io::Result<()> {
+    let mut words = pager.split_whitespace();
+    let Some(program) = words.next() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "PAGER is empty",
+        ));
+    };
+
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    // Pagers like less without -R show styling as raw escape codes, so it's left out
+    let styling = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closing early (e.g. q in less) shows up as a broken pipe, which is fine
+        for line in lines {
+            if writeln!(stdin, "{}", styling.replace_all(line, "")).is_err() {
+                break;
+            }
+        }
+    }
+
+    child.wait()?;
+    Ok(())
+}
+
+fn internal(lines: &[String], height: usize) -> io::Result<()> {
+    // Leave the bottom row for the prompt
+    let mut shown = print_lines(lines, 0, height - 1);
+
+    while shown < lines.len() {
+        print!("{}", PROMPT);
+        io::stdout().flush()?;
+
+        let key = read_key();
+        print!("\r{}\r", " ".repeat(PROMPT.len()));
+
+        match key? {
+            KeyCode::Char(' ') => shown = print_lines(lines, shown, height - 1),
+            KeyCode::Enter => shown = print_lines(lines, shown, 1),
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            _ => {}
+        }
+    }
+
+    io::stdout().flush()
+}
+
+// Print up to `count` lines from `start`, returning how far the output got
+fn print_lines(lines: &[String], start: usize, count: usize) -> usize {
+    let end = (start + count).min(lines.len());
+    for line in &lines[start..end] {
+        println!("{}", line);
+    }
+
+    end
+}
+
+// Wait for a single key press. Raw mode is only on while waiting, since it also turns
+// off the newline translation the printed lines rely on.
+pub fn read_key() -> io::Result<KeyCode> {
+    terminal::enable_raw_mode()?;
+    let key = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                // Raw mode swallows Ctrl+C, so it comes back as q, for quit
+                let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                break Ok(match key.code {
+                    KeyCode::Char('c') if ctrl => KeyCode::Char('q'),
+                    code => code,
+                });
+            }
+            Ok(_) => continue,
+            Err(e) => break Err(e),
+        }
+    };
+    terminal::disable_raw_mode()?;
+
+    key
+}
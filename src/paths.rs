@@ -0,0 +1,31 @@
+use directories::ProjectDirs;
+use std::path::PathBuf;
+
+// Where glide keeps its files, following the platform's conventions
+// ($XDG_CONFIG_HOME and $XDG_DATA_HOME on Linux, Application Support on macOS,
+// AppData on Windows) unless --config-dir moves everything into one directory
+pub struct Paths {
+    pub config_file: PathBuf,
+    pub transfer_log: PathBuf,
+    pub blocklist: PathBuf,
+}
+
+impl Paths {
+    // None if the platform directories can't be found, e.g. without a home directory
+    pub fn resolve(config_dir: Option<PathBuf>) -> Option<Paths> {
+        if let Some(dir) = config_dir {
+            return Some(Paths {
+                config_file: dir.join("config.toml"),
+                transfer_log: dir.join("transfers.log"),
+                blocklist: dir.join("blocked.txt"),
+            });
+        }
+
+        let dirs = ProjectDirs::from("", "", "glide")?;
+        Some(Paths {
+            config_file: dirs.config_dir().join("config.toml"),
+            transfer_log: dirs.data_dir().join("transfers.log"),
+            blocklist: dirs.data_dir().join("blocked.txt"),
+        })
+    }
+}
@@ -0,0 +1,83 @@
+use crate::format::format_bytes;
+use crossterm::terminal::{Clear, ClearType};
+use std::cell::Cell;
+use std::io::{self, IsTerminal, Write};
+use std::time::Instant;
+
+const WIDTH: usize = 30;
+
+// An in-place bar for a download, redrawn each time more of the file has arrived.
+// The state is in cells so the bar can be cleared from inside another callback of
+// the same transfer, such as the collision prompt.
+pub struct Progress {
+    enabled: bool,
+    drawn: Cell<bool>,
+    // When the previous sample was taken and how much had arrived by then
+    last: Cell<(Instant, u64)>,
+    // Bytes per second, smoothed so the ETA doesn't jump with every sample
+    rate: Cell<f64>,
+}
+
+impl Progress {
+    // Only drawn for a person watching a terminal. Anywhere else the finished
+    // transfer's summary line is all that's printed.
+    pub fn new(quiet: bool) -> Progress {
+        Progress {
+            enabled: !quiet && io::stdout().is_terminal(),
+            drawn: Cell::new(false),
+            last: Cell::new((Instant::now(), 0)),
+            rate: Cell::new(0.0),
+        }
+    }
+
+    pub fn update(&self, received: u64, total: u64) {
+        if !self.enabled || total == 0 {
+            return;
+        }
+
+        let (then, before) = self.last.get();
+        let secs = then.elapsed().as_secs_f64();
+        if secs > 0.0 {
+            let sample = received.saturating_sub(before) as f64 / secs;
+            let rate = self.rate.get();
+            self.rate.set(if rate > 0.0 {
+                rate * 0.7 + sample * 0.3
+            } else {
+                sample
+            });
+            self.last.set((Instant::now(), received));
+        }
+
+        let fraction = (received as f64 / total as f64).min(1.0);
+        let filled = (fraction * WIDTH as f64) as usize;
+        let rate = self.rate.get();
+        let eta = if rate > 0.0 {
+            let secs = (total.saturating_sub(received) as f64 / rate).ceil() as u64;
+            format!("ETA {}:{:02}", secs / 60, secs % 60)
+        } else {
+            "ETA --:--".to_string()
+        };
+
+        print!(
+            "\r{}[{}{}] {:>3}%  {} / {}  {}/s  {}",
+            Clear(ClearType::CurrentLine),
+            "=".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            (fraction * 100.0) as u64,
+            format_bytes(received),
+            format_bytes(total),
+            format_bytes(rate as u64),
+            eta
+        );
+        let _ = io::stdout().flush();
+        self.drawn.set(true);
+    }
+
+    // Leave the line clean for whatever is printed next
+    pub fn clear(&self) {
+        if self.drawn.replace(false) {
+            print!("\r{}", Clear(ClearType::CurrentLine));
+            let _ = io::stdout().flush();
+        }
+    }
+}
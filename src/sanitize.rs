@@ -0,0 +1,72 @@
+// Separators of the local platform. On Unix '\' and ':' are ordinary characters, and
+// names like "my:notes.txt" have to keep them. On Windows ':' also covers drive
+// prefixes like "C:evil.txt".
+#[cfg(windows)]
+const SEPARATORS: &[char] = &['/', '\\', ':'];
+#[cfg(not(windows))]
+const SEPARATORS: &[char] = &['/'];
+
+// Reduce a file name to its last path component, so it can only ever name a file
+// inside the directory it's saved to
+pub fn sanitize_filename(name: &str) -> Result<String, String> {
+    let base = name.rsplit(SEPARATORS).next().unwrap_or_default().trim();
+
+    if base.is_empty() || base == "." || base == ".." {
+        return Err(format!("'{}' isn't a usable file name", name));
+    }
+
+    Ok(base.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_filename;
+
+    #[test]
+    fn keeps_a_plain_name() {
+        assert_eq!(sanitize_filename("photo.jpg").unwrap(), "photo.jpg");
+    }
+
+    #[test]
+    fn strips_an_absolute_path() {
+        assert_eq!(sanitize_filename("/etc/passwd").unwrap(), "passwd");
+    }
+
+    #[test]
+    fn strips_parent_directories() {
+        assert_eq!(sanitize_filename("../../evil").unwrap(), "evil");
+    }
+
+    #[test]
+    fn rejects_names_that_are_only_a_path() {
+        assert!(sanitize_filename("..").is_err());
+        assert!(sanitize_filename("dir/..").is_err());
+        assert!(sanitize_filename("dir/").is_err());
+        assert!(sanitize_filename("  ").is_err());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn strips_backslash_parent_directories() {
+        assert_eq!(sanitize_filename("..\\evil").unwrap(), "evil");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn strips_a_drive_prefix() {
+        assert_eq!(sanitize_filename("C:evil.txt").unwrap(), "evil.txt");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn backslashes_are_part_of_the_name() {
+        assert_eq!(sanitize_filename("..\\evil").unwrap(), "..\\evil");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn colons_are_part_of_the_name() {
+        assert_eq!(sanitize_filename("C:evil.txt").unwrap(), "C:evil.txt");
+        assert_eq!(sanitize_filename("my:notes.txt").unwrap(), "my:notes.txt");
+    }
+}
@@ -0,0 +1,52 @@
+use std::io::{self, IsTerminal, Write};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+const FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+
+// An in-place spinner drawn by a background task while the client waits on the network
+pub struct Spinner {
+    running: Option<(oneshot::Sender<()>, JoinHandle<()>)>,
+}
+
+impl Spinner {
+    // Only drawn for a person watching a terminal, never under --json or --quiet
+    pub fn start(message: &str, quiet: bool) -> Spinner {
+        if quiet || !io::stdout().is_terminal() {
+            return Spinner { running: None };
+        }
+
+        let (stop, mut stopped) = oneshot::channel::<()>();
+        let message = message.to_string();
+
+        let handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(100));
+
+            for frame in FRAMES.iter().cycle() {
+                tokio::select! {
+                    _ = &mut stopped => break,
+                    _ = interval.tick() => {
+                        print!("\r{} {}", frame, message);
+                        let _ = io::stdout().flush();
+                    }
+                }
+            }
+
+            // Leave the line clean for whatever is printed next
+            print!("\r{}\r", " ".repeat(message.len() + 2));
+            let _ = io::stdout().flush();
+        });
+
+        Spinner {
+            running: Some((stop, handle)),
+        }
+    }
+
+    pub async fn stop(mut self) {
+        if let Some((stop, handle)) = self.running.take() {
+            let _ = stop.send(());
+            let _ = handle.await;
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::io;
+use std::sync::mpsc;
+use std::thread;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+// Lines from stdin, read one at a time on a dedicated thread when asked for. A wait
+// for a line can be given up, e.g. when a heartbeat fails, without losing the line:
+// it goes to whichever caller reads next, the command loop or the username prompt.
+// A plain thread also doesn't hold up the runtime's shutdown while it's blocked.
+pub struct StdinLines {
+    requests: mpsc::Sender<()>,
+    lines: UnboundedReceiver<io::Result<String>>,
+    // Set while a line has been asked for but not taken
+    outstanding: bool,
+}
+
+impl StdinLines {
+    pub fn spawn() -> StdinLines {
+        let (requests, wanted) = mpsc::channel::<()>();
+        let (send, lines) = unbounded_channel();
+
+        thread::spawn(move || {
+            while wanted.recv().is_ok() {
+                let mut line = String::new();
+                let read = io::stdin().read_line(&mut line).map(|_| line);
+                if send.send(read).is_err() {
+                    break;
+                }
+            }
+        });
+
+        StdinLines {
+            requests,
+            lines,
+            outstanding: false,
+        }
+    }
+
+    // Returns an empty line at EOF, like `read_line`. Dropping the future before it
+    // finishes keeps the line for the next call.
+    pub async fn read_line(&mut self) -> io::Result<String> {
+        if !self.outstanding {
+            // The thread only goes away with the receiver, so this can't fail
+            let _ = self.requests.send(());
+            self.outstanding = true;
+        }
+
+        let line = self.lines.recv().await.unwrap_or_else(|| Ok(String::new()));
+        self.outstanding = false;
+        line
+    }
+}
@@ -0,0 +1,146 @@
+// Split a command line into words, keeping quoted text together so paths with spaces
+// survive: `glide "my file.txt" @bob` gives ["glide", "my file.txt", "@bob"].
+// Single quotes are literal. A backslash escapes a quote or a space, and is kept as-is
+// anywhere else so Windows paths like C:\Users\bob need no escaping.
+pub fn tokenize(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    // Set when the current token exists, even if it's an empty pair of quotes
+    let mut in_token = false;
+    let mut quote = None;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('\''), c) => token.push(c),
+            (_, '\\') => match chars.peek() {
+                Some(&next) if next == '"' || (quote.is_none() && is_escapable(next)) => {
+                    token.push(next);
+                    chars.next();
+                }
+                _ => token.push('\\'),
+            },
+            (Some(_), c) => token.push(c),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, c) if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut token));
+                    in_token = false;
+                }
+                continue;
+            }
+            (None, c) => token.push(c),
+        }
+        in_token = true;
+    }
+
+    if let Some(quote) = quote {
+        return Err(format!("Missing closing {} quote", quote));
+    }
+    if in_token {
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn is_escapable(c: char) -> bool {
+    c == '\'' || c.is_whitespace()
+}
+
+// Quote a word if needed so `tokenize` gives it back unchanged
+pub fn quote(word: &str) -> String {
+    if !word.is_empty() && !word.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+        return word.to_string();
+    }
+
+    format!("\"{}\"", word.replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_quotes_keep_a_path_with_spaces_together() {
+        assert_eq!(
+            tokenize("glide \"a b.txt\" @bob").unwrap(),
+            ["glide", "a b.txt", "@bob"]
+        );
+    }
+
+    #[test]
+    fn escaped_quotes_are_part_of_the_word() {
+        assert_eq!(
+            tokenize(r#"glide "say \"hi\".txt" @bob"#).unwrap(),
+            ["glide", "say \"hi\".txt", "@bob"]
+        );
+        assert_eq!(
+            tokenize(r"glide it\'s.txt @bob").unwrap(),
+            ["glide", "it's.txt", "@bob"]
+        );
+        assert_eq!(
+            tokenize(r"glide a\ b.txt @bob").unwrap(),
+            ["glide", "a b.txt", "@bob"]
+        );
+    }
+
+    #[test]
+    fn backslashes_elsewhere_are_kept() {
+        assert_eq!(
+            tokenize(r"glide C:\Users\bob\a.txt @bob").unwrap(),
+            ["glide", r"C:\Users\bob\a.txt", "@bob"]
+        );
+    }
+
+    #[test]
+    fn an_unclosed_quote_is_an_error() {
+        assert!(tokenize("glide \"a b.txt @bob").is_err());
+    }
+
+    #[test]
+    fn quoted_words_come_back_unchanged() {
+        for word in ["a b.txt", "say \"hi\".txt", "it's.txt", ""] {
+            assert_eq!(tokenize(&quote(word)).unwrap(), [word]);
+        }
+    }
+
+    #[test]
+    fn repeated_spaces_separate_words_once() {
+        assert_eq!(
+            tokenize("glide   file.txt    @bob").unwrap(),
+            ["glide", "file.txt", "@bob"]
+        );
+    }
+
+    #[test]
+    fn tabs_and_surrounding_whitespace_are_ignored() {
+        assert_eq!(
+            tokenize(" \tglide\tfile.txt \t @bob\t ").unwrap(),
+            ["glide", "file.txt", "@bob"]
+        );
+    }
+
+    #[test]
+    fn whitespace_inside_quotes_is_kept() {
+        assert_eq!(
+            tokenize("glide   \"my   file.txt\"   @bob").unwrap(),
+            ["glide", "my   file.txt", "@bob"]
+        );
+        assert_eq!(
+            tokenize("glide '\tindented.txt' @bob").unwrap(),
+            ["glide", "\tindented.txt", "@bob"]
+        );
+    }
+
+    #[test]
+    fn empty_quotes_are_an_empty_word() {
+        assert_eq!(tokenize("glide \"\" @bob").unwrap(), ["glide", "", "@bob"]);
+    }
+
+    #[test]
+    fn blank_input_has_no_words() {
+        assert!(tokenize(" \t ").unwrap().is_empty());
+    }
+}
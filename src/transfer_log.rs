@@ -0,0 +1,50 @@
+use crate::events::Direction;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Completed,
+    Failed,
+    Cancelled,
+    Skipped,
+}
+
+// One finished or failed transfer, appended to the log as a single JSON line
+#[derive(Serialize)]
+pub struct Entry<'a> {
+    pub direction: Direction,
+    pub peer: &'a str,
+    pub file: &'a str,
+    pub size: Option<u64>,
+    pub status: Status,
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    timestamp: u64,
+    #[serde(flatten)]
+    entry: &'a Entry<'a>,
+}
+
+pub fn append(path: &Path, entry: &Entry) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0);
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        file,
+        "{}",
+        serde_json::to_string(&Line { timestamp, entry })?
+    )
+}
@@ -0,0 +1,43 @@
+use crossterm::style::Stylize;
+use std::io::{self, IsTerminal};
+
+const GAP: usize = 2;
+
+// Lay out connected users in columns that fill the terminal, top to bottom like `ls`,
+// with the current user highlighted. Anything other than a terminal, or `plain`, gets
+// one user per line so the output stays easy to parse.
+pub fn lines(users: &[String], me: &str, plain: bool) -> Vec<String> {
+    if plain || !io::stdout().is_terminal() {
+        let mut lines = vec!["Connected users:".to_string()];
+        lines.extend(users.iter().map(|user| format!(" @{}", user)));
+        return lines;
+    }
+
+    let mut lines = vec![match users.len() {
+        1 => "1 user online".to_string(),
+        count => format!("{} users online", count),
+    }];
+
+    let cell = users.iter().map(|user| user.len() + 1).max().unwrap_or(0) + GAP;
+    let width = crossterm::terminal::size()
+        .map(|(columns, _)| columns as usize)
+        .unwrap_or(80);
+    let columns = (width / cell).max(1);
+    let rows = users.len().div_ceil(columns);
+
+    for row in 0..rows {
+        let mut line = String::new();
+        for user in users.iter().skip(row).step_by(rows) {
+            let name = format!("@{}", user);
+            let padding = " ".repeat(cell - name.len());
+            if user == me {
+                line.push_str(&format!("{}{}", name.bold().green(), padding));
+            } else {
+                line.push_str(&format!("{}{}", name, padding));
+            }
+        }
+        lines.push(line.trim_end().to_string());
+    }
+
+    lines
+}
@@ -0,0 +1,115 @@
+use std::error::Error;
+use std::fmt;
+
+const MAX_LEN: usize = 10;
+
+#[derive(Debug, PartialEq)]
+pub enum UsernameError {
+    Empty,
+    TooLong,
+    IllegalCharacter(char),
+    EdgePeriod,
+    ConsecutivePeriods,
+}
+
+impl fmt::Display for UsernameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UsernameError::Empty => write!(f, "Usernames can't be empty"),
+            UsernameError::TooLong => {
+                write!(f, "Usernames can be at most {} characters long", MAX_LEN)
+            }
+            UsernameError::IllegalCharacter(c) => write!(
+                f,
+                "'{}' isn't allowed, only letters, digits and periods (.) are",
+                c
+            ),
+            UsernameError::EdgePeriod => {
+                write!(f, "Usernames can't start or end with a period (.)")
+            }
+            UsernameError::ConsecutivePeriods => {
+                write!(f, "Usernames can't contain consecutive periods (..)")
+            }
+        }
+    }
+}
+
+impl Error for UsernameError {}
+
+pub fn validate_username(username: &str) -> Result<(), UsernameError> {
+    if username.is_empty() {
+        return Err(UsernameError::Empty);
+    }
+
+    if let Some(c) = username
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && *c != '.')
+    {
+        return Err(UsernameError::IllegalCharacter(c));
+    }
+
+    // Only ASCII is left at this point, so bytes and characters agree
+    if username.len() > MAX_LEN {
+        return Err(UsernameError::TooLong);
+    }
+
+    if username.starts_with('.') || username.ends_with('.') {
+        return Err(UsernameError::EdgePeriod);
+    }
+
+    if username.contains("..") {
+        return Err(UsernameError::ConsecutivePeriods);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_a_valid_name() {
+        assert_eq!(validate_username("alice.b"), Ok(()));
+        assert_eq!(validate_username("abcdefghij"), Ok(()));
+    }
+
+    #[test]
+    fn rejects_an_empty_name() {
+        assert_eq!(validate_username(""), Err(UsernameError::Empty));
+    }
+
+    #[test]
+    fn rejects_a_name_over_the_limit() {
+        assert_eq!(
+            validate_username("abcdefghijk"),
+            Err(UsernameError::TooLong)
+        );
+    }
+
+    #[test]
+    fn rejects_an_illegal_character() {
+        assert_eq!(
+            validate_username("al ice"),
+            Err(UsernameError::IllegalCharacter(' '))
+        );
+        assert_eq!(
+            validate_username("élodie"),
+            Err(UsernameError::IllegalCharacter('é'))
+        );
+    }
+
+    #[test]
+    fn rejects_a_period_at_either_end() {
+        assert_eq!(validate_username(".alice"), Err(UsernameError::EdgePeriod));
+        assert_eq!(validate_username("alice."), Err(UsernameError::EdgePeriod));
+    }
+
+    #[test]
+    fn rejects_consecutive_periods() {
+        assert_eq!(
+            validate_username("al..ice"),
+            Err(UsernameError::ConsecutivePeriods)
+        );
+    }
+}
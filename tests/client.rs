@@ -0,0 +1,478 @@
+// Drives `Client` against a mock server on a local port. The mock speaks the protocol
+// through the same utils types the real server uses, one scripted exchange per test.
+use glide_cli::{Client, Collision, Login};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use utils::commands::Command;
+use utils::protocol::Transmission;
+use utils::transfers;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn listen() -> (TcpListener, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    (listener, address)
+}
+
+async fn send(stream: &mut TcpStream, transmission: Transmission) {
+    stream
+        .write_all(transmission.to_bytes().as_slice())
+        .await
+        .unwrap();
+}
+
+async fn read(stream: &mut TcpStream) -> Transmission {
+    Transmission::from_stream(stream).await.unwrap()
+}
+
+// Accept one client and log it in as `username`
+async fn accept_login(listener: &TcpListener, username: &str) -> TcpStream {
+    let (mut stream, _) = listener.accept().await.unwrap();
+    match read(&mut stream).await {
+        Transmission::Username(name) => assert_eq!(name.to_string(), username),
+        other => panic!("expected a username, got {:?}", other),
+    }
+    send(&mut stream, Transmission::UsernameOk).await;
+    stream
+}
+
+async fn connect_login(address: &str, username: &str) -> Client {
+    let mut client = Client::connect(address, TIMEOUT, TIMEOUT).await.unwrap();
+    assert_eq!(client.login(username).await.unwrap(), Login::Accepted);
+    client
+}
+
+fn files_in(dir: &Path) -> Vec<String> {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect()
+}
+
+// Transfer frames as a sender would put them on the wire (see protocol.txt), for
+// names and sizes the utils helpers would never produce
+fn metadata_frame(name: &str, size: u32) -> Vec<u8> {
+    let mut frame = vec![5];
+    frame.extend_from_slice(name.as_bytes());
+    frame.push(0);
+    frame.extend_from_slice(&size.to_be_bytes());
+    frame
+}
+
+fn chunk_frame(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut frame = vec![6];
+    frame.extend_from_slice(name.as_bytes());
+    frame.push(0);
+    frame.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    frame.extend_from_slice(data);
+    frame
+}
+
+// Accept an ok from "bob" as if from "alice" and answer with raw transfer frames,
+// returning what receiving into `output` made of them
+async fn receive_frames(
+    output: &Path,
+    frames: &[Vec<u8>],
+) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let (listener, address) = listen().await;
+
+    let server = async {
+        let mut stream = accept_login(&listener, "bob").await;
+        read(&mut stream).await;
+        send(&mut stream, Transmission::OkSuccess).await;
+        for frame in frames {
+            stream.write_all(frame).await.unwrap();
+        }
+    };
+    let client = async {
+        let mut client = connect_login(&address, "bob").await;
+        client.request(Command::parse("ok @alice")).await.unwrap();
+        client
+            .receive_file(
+                output,
+                None,
+                TIMEOUT,
+                |_| Ok(Collision::Overwrite),
+                |_, _| {},
+            )
+            .await
+    };
+
+    let ((), result) = tokio::join!(server, client);
+    result
+}
+
+#[tokio::test]
+async fn login_reports_a_taken_username() {
+    let (listener, address) = listen().await;
+
+    let server = async {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        read(&mut stream).await;
+        send(&mut stream, Transmission::UsernameTaken).await;
+    };
+    let client = async {
+        let mut client = Client::connect(&address, TIMEOUT, TIMEOUT).await.unwrap();
+        client.login("alice").await.unwrap()
+    };
+
+    let ((), login) = tokio::join!(server, client);
+    assert_eq!(login, Login::Taken);
+}
+
+#[tokio::test]
+async fn lists_connected_users() {
+    let (listener, address) = listen().await;
+
+    let server = async {
+        let mut stream = accept_login(&listener, "alice").await;
+        match read(&mut stream).await {
+            Transmission::Command(Command::List) => {}
+            other => panic!("expected list, got {:?}", other),
+        }
+        send(
+            &mut stream,
+            Transmission::ConnectedUsers(vec!["alice".to_string(), "bob".to_string()]),
+        )
+        .await;
+    };
+    let client = async {
+        let mut client = connect_login(&address, "alice").await;
+        client.list_users().await.unwrap()
+    };
+
+    let ((), users) = tokio::join!(server, client);
+    assert_eq!(users, ["alice", "bob"]);
+}
+
+#[tokio::test]
+async fn uploads_a_file_after_the_glide_request_is_accepted() {
+    let (listener, address) = listen().await;
+    let source = tempfile::tempdir().unwrap();
+    let received = tempfile::tempdir().unwrap();
+    let path = source.path().join("report.txt");
+    std::fs::write(&path, b"quarterly numbers").unwrap();
+    let path = path.to_string_lossy().to_string();
+
+    let server = async {
+        let mut stream = accept_login(&listener, "alice").await;
+        match read(&mut stream).await {
+            Transmission::Command(Command::Glide { to, .. }) => {
+                assert_eq!(to.to_string().trim_start_matches('@'), "bob")
+            }
+            other => panic!("expected glide, got {:?}", other),
+        }
+        send(&mut stream, Transmission::GlideRequestSent).await;
+
+        let dir = received.path().to_string_lossy().to_string();
+        transfers::receive_file(&mut stream, &dir).await.unwrap();
+    };
+    let client = async {
+        let mut client = connect_login(&address, "alice").await;
+        let command = Command::parse(&format!("glide {} @bob", path));
+        match client.request(command).await.unwrap() {
+            Transmission::GlideRequestSent => {}
+            other => panic!("expected the request to be accepted, got {:?}", other),
+        }
+        client.send_file(&path).await.unwrap();
+    };
+
+    tokio::join!(server, client);
+    assert_eq!(
+        std::fs::read(received.path().join("report.txt")).unwrap(),
+        b"quarterly numbers"
+    );
+}
+
+#[tokio::test]
+async fn downloads_an_accepted_request() {
+    let (listener, address) = listen().await;
+    let source = tempfile::tempdir().unwrap();
+    let output = tempfile::tempdir().unwrap();
+    let path = source.path().join("photo.jpg");
+    std::fs::write(&path, b"not really a photo").unwrap();
+    let path = path.to_string_lossy().to_string();
+
+    let server = async {
+        let mut stream = accept_login(&listener, "bob").await;
+        match read(&mut stream).await {
+            Transmission::Command(Command::Ok(_)) => {}
+            other => panic!("expected ok, got {:?}", other),
+        }
+        send(&mut stream, Transmission::OkSuccess).await;
+        transfers::send_file(&mut stream, &path).await.unwrap();
+    };
+    let client = async {
+        let mut client = connect_login(&address, "bob").await;
+        match client.request(Command::parse("ok @alice")).await.unwrap() {
+            Transmission::OkSuccess => {}
+            other => panic!("expected the ok to succeed, got {:?}", other),
+        }
+        client
+            .receive_file(
+                output.path(),
+                None,
+                TIMEOUT,
+                |_| Ok(Collision::Skip),
+                |_, _| {},
+            )
+            .await
+            .unwrap()
+    };
+
+    let ((), saved) = tokio::join!(server, client);
+    let saved = saved.expect("the file shouldn't have been skipped");
+    assert_eq!(saved, output.path().join("photo.jpg"));
+    assert_eq!(std::fs::read(saved).unwrap(), b"not really a photo");
+    assert_eq!(files_in(output.path()), ["photo.jpg"]);
+}
+
+#[tokio::test]
+async fn a_disconnect_before_the_file_leaves_nothing_behind() {
+    let (listener, address) = listen().await;
+    let output = tempfile::tempdir().unwrap();
+
+    let server = async {
+        let mut stream = accept_login(&listener, "bob").await;
+        read(&mut stream).await;
+        send(&mut stream, Transmission::OkSuccess).await;
+        // Dropping the stream here closes the connection before any metadata
+    };
+    let client = async {
+        let mut client = connect_login(&address, "bob").await;
+        client.request(Command::parse("ok @alice")).await.unwrap();
+        client
+            .receive_file(
+                output.path(),
+                None,
+                TIMEOUT,
+                |_| Ok(Collision::Skip),
+                |_, _| {},
+            )
+            .await
+    };
+
+    let ((), result) = tokio::join!(server, client);
+    assert!(result.is_err());
+    assert!(files_in(output.path()).is_empty());
+}
+
+#[tokio::test]
+async fn a_file_shorter_than_announced_reports_the_shortfall() {
+    let (listener, address) = listen().await;
+    let output = tempfile::tempdir().unwrap();
+
+    let server = async {
+        let mut stream = accept_login(&listener, "bob").await;
+        read(&mut stream).await;
+        send(&mut stream, Transmission::OkSuccess).await;
+        stream
+            .write_all(&metadata_frame("photo.jpg", 100))
+            .await
+            .unwrap();
+        stream
+            .write_all(&chunk_frame("photo.jpg", b"the first 25 of 100 bytes"))
+            .await
+            .unwrap();
+    };
+    let client = async {
+        let mut client = connect_login(&address, "bob").await;
+        client.request(Command::parse("ok @alice")).await.unwrap();
+        client
+            .receive_file(
+                output.path(),
+                None,
+                TIMEOUT,
+                |_| Ok(Collision::Overwrite),
+                |_, _| {},
+            )
+            .await
+    };
+
+    let ((), result) = tokio::join!(server, client);
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("25 of 100 bytes"), "{}", error);
+}
+
+#[tokio::test]
+async fn a_disconnect_midway_through_the_file_leaves_nothing_behind() {
+    let (listener, address) = listen().await;
+    let output = tempfile::tempdir().unwrap();
+
+    let server = async {
+        let mut stream = accept_login(&listener, "bob").await;
+        read(&mut stream).await;
+        send(&mut stream, Transmission::OkSuccess).await;
+        stream
+            .write_all(&metadata_frame("photo.jpg", 100))
+            .await
+            .unwrap();
+        stream
+            .write_all(&chunk_frame("photo.jpg", b"the first 25 of 100 bytes"))
+            .await
+            .unwrap();
+        // Dropping the stream here closes the connection with the rest still to come
+    };
+    let client = async {
+        let mut client = connect_login(&address, "bob").await;
+        client.request(Command::parse("ok @alice")).await.unwrap();
+        client
+            .receive_file(
+                output.path(),
+                None,
+                TIMEOUT,
+                |_| Ok(Collision::Overwrite),
+                |_, _| {},
+            )
+            .await
+    };
+
+    let ((), result) = tokio::join!(server, client);
+    assert!(result.is_err());
+    assert!(!output.path().join("photo.jpg").exists());
+    assert!(files_in(output.path()).is_empty());
+}
+
+#[tokio::test]
+async fn malformed_metadata_is_an_error() {
+    let (listener, address) = listen().await;
+    let output = tempfile::tempdir().unwrap();
+
+    let server = async {
+        let mut stream = accept_login(&listener, "bob").await;
+        read(&mut stream).await;
+        send(&mut stream, Transmission::OkSuccess).await;
+        // A metadata frame (code 5) whose file name is never terminated
+        stream.write_all(b"\x05photo.jpg").await.unwrap();
+    };
+    let client = async {
+        let mut client = connect_login(&address, "bob").await;
+        client.request(Command::parse("ok @alice")).await.unwrap();
+        client
+            .receive_file(
+                output.path(),
+                None,
+                TIMEOUT,
+                |_| Ok(Collision::Skip),
+                |_, _| {},
+            )
+            .await
+    };
+
+    let ((), result) = tokio::join!(server, client);
+    assert!(result.is_err());
+    assert!(files_in(output.path()).is_empty());
+}
+
+#[tokio::test]
+async fn save_as_replaces_the_senders_file_name() {
+    let (listener, address) = listen().await;
+    let source = tempfile::tempdir().unwrap();
+    let output = tempfile::tempdir().unwrap();
+    let path = source.path().join("IMG_0001.jpg");
+    std::fs::write(&path, b"holiday").unwrap();
+    let path = path.to_string_lossy().to_string();
+
+    let server = async {
+        let mut stream = accept_login(&listener, "bob").await;
+        read(&mut stream).await;
+        send(&mut stream, Transmission::OkSuccess).await;
+        transfers::send_file(&mut stream, &path).await.unwrap();
+    };
+    let client = async {
+        let mut client = connect_login(&address, "bob").await;
+        client.request(Command::parse("ok @alice")).await.unwrap();
+        client
+            .receive_file(
+                output.path(),
+                Some("beach.jpg"),
+                TIMEOUT,
+                |_| Ok(Collision::Skip),
+                |_, _| {},
+            )
+            .await
+            .unwrap()
+    };
+
+    let ((), saved) = tokio::join!(server, client);
+    assert_eq!(saved, Some(output.path().join("beach.jpg")));
+    assert_eq!(files_in(output.path()), ["beach.jpg"]);
+}
+
+#[tokio::test]
+async fn a_file_name_climbing_out_of_the_directory_is_refused() {
+    let output = tempfile::tempdir().unwrap();
+    let name = "../../glide-climbing-out.txt";
+
+    let result = receive_frames(
+        output.path(),
+        &[metadata_frame(name, 4), chunk_frame(name, b"evil")],
+    )
+    .await;
+
+    assert!(result.is_err());
+    assert!(files_in(output.path()).is_empty());
+    let parent = output.path().parent().unwrap();
+    assert!(!parent.join("glide-climbing-out.txt").exists());
+    assert!(!parent
+        .parent()
+        .unwrap()
+        .join("glide-climbing-out.txt")
+        .exists());
+}
+
+#[tokio::test]
+async fn an_absolute_file_name_is_refused() {
+    let output = tempfile::tempdir().unwrap();
+    let elsewhere = tempfile::tempdir().unwrap();
+    let target = elsewhere.path().join("evil.txt");
+    let name = target.to_string_lossy().to_string();
+
+    let result = receive_frames(
+        output.path(),
+        &[metadata_frame(&name, 4), chunk_frame(&name, b"evil")],
+    )
+    .await;
+
+    assert!(result.is_err());
+    assert!(files_in(output.path()).is_empty());
+    assert!(!target.exists());
+}
+
+#[tokio::test]
+async fn data_for_a_different_file_name_is_refused() {
+    let output = tempfile::tempdir().unwrap();
+    let elsewhere = tempfile::tempdir().unwrap();
+    let target = elsewhere.path().join("evil.txt");
+
+    let result = receive_frames(
+        output.path(),
+        &[
+            metadata_frame("photo.jpg", 4),
+            chunk_frame(&target.to_string_lossy(), b"evil"),
+        ],
+    )
+    .await;
+
+    assert!(result.is_err());
+    assert!(files_in(output.path()).is_empty());
+    assert!(!target.exists());
+}
+
+#[tokio::test]
+async fn an_empty_file_arrives_empty() {
+    let output = tempfile::tempdir().unwrap();
+
+    let received = receive_frames(output.path(), &[metadata_frame("empty.txt", 0)])
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(received, output.path().join("empty.txt"));
+    assert_eq!(std::fs::metadata(&received).unwrap().len(), 0);
+    assert_eq!(files_in(output.path()), ["empty.txt"]);
+}